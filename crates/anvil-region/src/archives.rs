@@ -0,0 +1,245 @@
+use crate::worlds::parse_region_file_name;
+use flate2::read::GzDecoder;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    fs::{remove_file, File},
+    io::{self, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+use zip::ZipArchive;
+
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+enum ArchiveReader {
+    /// Index of each file in the zip, as the names of the entries are not normalized
+    Zip(ZipArchive<Box<dyn ReadSeek>>, BTreeMap<String, usize>),
+    /// Position and size of each file in the tar data
+    Tar(Box<dyn ReadSeek>, BTreeMap<String, (u64, u64)>),
+}
+
+/// A zip or tar archive whose files are read on demand, without extracting it.
+/// Gzipped tar archives cannot be read at random positions, so they are decompressed
+/// to a temporary file first
+pub struct Archive {
+    /// Path of the archive file, None if it was read from memory
+    pub path: Option<PathBuf>,
+    /// Size of each file, with `/` separated paths
+    files: BTreeMap<String, u64>,
+    reader: Mutex<ArchiveReader>,
+    temp_file: Option<PathBuf>,
+}
+
+impl fmt::Debug for Archive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Archive")
+            .field("path", &self.path)
+            .field("files", &self.files.len())
+            .finish()
+    }
+}
+
+impl Drop for Archive {
+    fn drop(&mut self) {
+        if let Some(temp_file) = &self.temp_file {
+            let _ = remove_file(temp_file);
+        }
+    }
+}
+
+/// Use `/` as separator and remove the leading `./` or `/`
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.strip_prefix("./").unwrap_or(&path);
+    path.trim_start_matches('/').to_string()
+}
+
+/// Extensions of the archives `Archive::open` reads
+pub const ARCHIVE_EXTENSIONS: [&str; 5] = [".zip", ".jar", ".tar", ".tar.gz", ".tgz"];
+
+/// Whether a file name ends with one of the archive extensions
+pub fn is_archive_path(path: &Path) -> bool {
+    let name = path.to_string_lossy().to_lowercase();
+    ARCHIVE_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+}
+
+impl Archive {
+    /// Open a `.zip`, `.jar`, `.tar`, `.tar.gz` or `.tgz` file
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Archive> {
+        let path = path.as_ref();
+        let name = path.to_string_lossy().to_lowercase();
+
+        let mut archive = if name.ends_with(".zip") || name.ends_with(".jar") {
+            Archive::from_zip(Box::new(File::open(path)?))?
+        } else if name.ends_with(".tar") {
+            Archive::from_tar(Box::new(File::open(path)?))?
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            let nanos = SystemTime::UNIX_EPOCH
+                .elapsed()
+                .map(|d| d.as_nanos())
+                .unwrap_or_default();
+            let temp_path =
+                std::env::temp_dir().join(format!("chunkmap-{}-{nanos}.tar", std::process::id()));
+
+            let mut temp = File::options()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&temp_path)?;
+
+            let result = io::copy(&mut GzDecoder::new(File::open(path)?), &mut temp)
+                .and_then(|_| temp.seek(SeekFrom::Start(0)))
+                .and_then(|_| Archive::from_tar(Box::new(temp)));
+
+            match result {
+                Ok(mut archive) => {
+                    archive.temp_file = Some(temp_path);
+                    archive
+                }
+                Err(e) => {
+                    let _ = remove_file(&temp_path);
+                    return Err(e);
+                }
+            }
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Unknown archive format for '{}'", path.display()),
+            ));
+        };
+
+        archive.path = Some(path.to_path_buf());
+        Ok(archive)
+    }
+
+    /// Read a zip archive from memory, e.g. a file uploaded in a browser
+    pub fn from_zip_bytes(data: Vec<u8>) -> io::Result<Archive> {
+        Archive::from_zip(Box::new(Cursor::new(data)))
+    }
+
+    pub fn from_zip(reader: Box<dyn ReadSeek>) -> io::Result<Archive> {
+        let mut zip = ZipArchive::new(reader)?;
+        let mut files = BTreeMap::new();
+        let mut indices = BTreeMap::new();
+
+        for i in 0..zip.len() {
+            let file = zip.by_index(i)?;
+            if file.is_file() {
+                let path = normalize_path(file.name());
+                files.insert(path.clone(), file.size());
+                indices.insert(path, i);
+            }
+        }
+
+        Ok(Archive {
+            path: None,
+            files,
+            reader: Mutex::new(ArchiveReader::Zip(zip, indices)),
+            temp_file: None,
+        })
+    }
+
+    /// Read an uncompressed tar archive. Only the position of each file is kept
+    pub fn from_tar(reader: Box<dyn ReadSeek>) -> io::Result<Archive> {
+        let mut tar = tar::Archive::new(reader);
+        let mut entries = BTreeMap::new();
+
+        for entry in tar.entries_with_seek()? {
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let path = normalize_path(&entry.path()?.to_string_lossy());
+            entries.insert(path, (entry.raw_file_position(), entry.size()));
+        }
+
+        let files = entries
+            .iter()
+            .map(|(path, (_, size))| (path.clone(), *size))
+            .collect();
+
+        Ok(Archive {
+            path: None,
+            files,
+            reader: Mutex::new(ArchiveReader::Tar(tar.into_inner(), entries)),
+            temp_file: None,
+        })
+    }
+
+    /// Paths and sizes of all the files of the archive
+    pub fn files(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.files.iter().map(|(path, size)| (path.as_str(), *size))
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.files.contains_key(&normalize_path(path))
+    }
+
+    pub fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let path = normalize_path(path);
+        let not_found = || {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{path}' not found in the archive"),
+            )
+        };
+
+        let mut reader = self
+            .reader
+            .lock()
+            .map_err(|_| io::Error::other("Archive reader poisoned"))?;
+
+        match &mut *reader {
+            ArchiveReader::Zip(zip, indices) => {
+                let &index = indices.get(&path).ok_or_else(not_found)?;
+                let mut file = zip.by_index(index)?;
+                let mut data = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut data)?;
+                Ok(data)
+            }
+            ArchiveReader::Tar(tar, entries) => {
+                let &(position, size) = entries.get(&path).ok_or_else(not_found)?;
+                tar.seek(SeekFrom::Start(position))?;
+
+                let mut data = vec![0; size as usize];
+                tar.read_exact(&mut data)?;
+                Ok(data)
+            }
+        }
+    }
+
+    /// Folders containing region files, e.g. `world/region` and `world/DIM-1/region`
+    pub fn region_folders(&self) -> Vec<String> {
+        let folders: BTreeSet<String> = self
+            .files
+            .keys()
+            .filter_map(|path| {
+                let (folder, name) = path.rsplit_once('/').unwrap_or(("", path));
+                parse_region_file_name(name).map(|_| folder.to_string())
+            })
+            .collect();
+
+        folders.into_iter().collect()
+    }
+
+    /// The region folder of the overworld: the shortest `region` folder outside of the
+    /// `DIM-1` and `DIM1` folders, or the first folder with region files
+    pub fn default_region_folder(&self) -> Option<String> {
+        let folders = self.region_folders();
+
+        folders
+            .iter()
+            .filter(|f| {
+                f.rsplit('/').next() == Some("region")
+                    && !f.split('/').any(|p| p.starts_with("DIM"))
+            })
+            .min_by_key(|f| f.len())
+            .or(folders.first())
+            .cloned()
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{blocks::BlockPosition, nbt::Compound};
+use fastnbt::Value;
+
+/// Extra data of a block, e.g. the items of a chest or the text of a sign
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEntity {
+    pub id: String,
+    pub position: BlockPosition,
+    /// All the other fields of the block entity
+    pub data: Compound,
+}
+
+impl BlockEntity {
+    /// NBT as stored in chunks, with the id and the position
+    pub fn to_nbt(&self) -> Value {
+        let mut compound = self.data.clone();
+        compound.insert("id".to_string(), Value::String(self.id.clone()));
+        compound.insert("x".to_string(), Value::Int(self.position.x));
+        compound.insert("y".to_string(), Value::Int(self.position.y));
+        compound.insert("z".to_string(), Value::Int(self.position.z));
+        Value::Compound(compound)
+    }
+}
+
+pub fn parse_block_entity(compound: &Compound) -> Result<BlockEntity, String> {
+    let id = match compound.get("id") {
+        Some(Value::String(v)) => v.clone(),
+        v => {
+            return Err(format!(
+                "'block_entity.id' not found or not a String. Got {v:?}"
+            ))
+        }
+    };
+
+    let mut coordinates = [0; 3];
+    for (i, key) in ["x", "y", "z"].iter().enumerate() {
+        coordinates[i] = match compound.get(*key) {
+            Some(Value::Int(v)) => *v,
+            v => {
+                return Err(format!(
+                    "'block_entity.{key}' not found or not an Int. Got {v:?}"
+                ))
+            }
+        };
+    }
+
+    let mut data = compound.clone();
+    for key in ["id", "x", "y", "z"] {
+        data.remove(key);
+    }
+
+    Ok(BlockEntity {
+        id,
+        position: BlockPosition {
+            x: coordinates[0],
+            y: coordinates[1],
+            z: coordinates[2],
+        },
+        data,
+    })
+}
+
+/// Parse the `block_entities` of a chunk
+pub fn parse_block_entities(root: &Compound) -> Result<Vec<BlockEntity>, String> {
+    let block_entities = match root.get("block_entities") {
+        Some(Value::List(v)) => v,
+        None => return Ok(Vec::new()),
+        v => return Err(format!("'block_entities' is not a List. Got {v:?}")),
+    };
+
+    block_entities
+        .iter()
+        .map(|v| match v {
+            Value::Compound(compound) => parse_block_entity(compound),
+            v => Err(format!("Block entity is not a Compound. Got {v:?}")),
+        })
+        .collect()
+}
@@ -1,4 +1,7 @@
-#[derive(Debug, Clone)]
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub struct BlockPosition {
     pub x: i32,
     pub y: i32,
@@ -11,4 +14,255 @@ pub struct Block {
     pub name: String,
     pub depth: u16,
     pub snowy: bool,
+    /// Block light of the air above the surface
+    pub block_light: u8,
+    /// Sky light of the air above the surface
+    pub sky_light: u8,
+}
+
+/// A palette entry with all its properties
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct BlockState {
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl BlockState {
+    pub fn new(name: &str) -> BlockState {
+        BlockState {
+            name: name.to_string(),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    pub fn air() -> BlockState {
+        BlockState::new("minecraft:air")
+    }
+
+    fn short_name(&self) -> &str {
+        self.name.strip_prefix("minecraft:").unwrap_or(&self.name)
+    }
+
+    pub fn is_air(&self) -> bool {
+        matches!(self.short_name(), "air" | "cave_air" | "void_air")
+    }
+
+    pub fn is_leaves(&self) -> bool {
+        self.short_name().ends_with("leaves")
+    }
+
+    /// Fluids, and blocks that are always or can be waterlogged
+    pub fn has_fluid(&self) -> bool {
+        matches!(
+            self.short_name(),
+            "water"
+                | "lava"
+                | "bubble_column"
+                | "kelp"
+                | "kelp_plant"
+                | "seagrass"
+                | "tall_seagrass"
+        ) || self.properties.get("waterlogged").map(|v| v.as_str()) == Some("true")
+    }
+
+    /// Whether the block stops entities, like the game's `blocksMotion`.
+    /// This is an approximation based on the names of the vanilla blocks without collisions
+    pub fn blocks_motion(&self) -> bool {
+        let name = self.short_name();
+
+        if self.is_air() || (self.has_fluid() && !self.properties.contains_key("waterlogged")) {
+            return false;
+        }
+
+        let non_solid = matches!(
+            name,
+            "short_grass"
+                | "grass"
+                | "tall_grass"
+                | "fern"
+                | "large_fern"
+                | "dead_bush"
+                | "dandelion"
+                | "poppy"
+                | "blue_orchid"
+                | "allium"
+                | "azure_bluet"
+                | "oxeye_daisy"
+                | "cornflower"
+                | "lily_of_the_valley"
+                | "wither_rose"
+                | "torchflower"
+                | "sunflower"
+                | "lilac"
+                | "rose_bush"
+                | "peony"
+                | "pitcher_plant"
+                | "pink_petals"
+                | "wildflowers"
+                | "leaf_litter"
+                | "brown_mushroom"
+                | "red_mushroom"
+                | "crimson_fungus"
+                | "warped_fungus"
+                | "crimson_roots"
+                | "warped_roots"
+                | "nether_sprouts"
+                | "sugar_cane"
+                | "vine"
+                | "glow_lichen"
+                | "sculk_vein"
+                | "hanging_roots"
+                | "cave_vines"
+                | "cave_vines_plant"
+                | "twisting_vines"
+                | "twisting_vines_plant"
+                | "weeping_vines"
+                | "weeping_vines_plant"
+                | "spore_blossom"
+                | "small_dripleaf"
+                | "big_dripleaf_stem"
+                | "sweet_berry_bush"
+                | "wheat"
+                | "carrots"
+                | "potatoes"
+                | "beetroots"
+                | "torch"
+                | "wall_torch"
+                | "soul_torch"
+                | "soul_wall_torch"
+                | "redstone_torch"
+                | "redstone_wall_torch"
+                | "redstone_wire"
+                | "lever"
+                | "tripwire"
+                | "tripwire_hook"
+                | "cobweb"
+                | "fire"
+                | "soul_fire"
+                | "light"
+                | "structure_void"
+                | "snow"
+                | "nether_portal"
+                | "end_portal"
+                | "end_gateway"
+                | "rail"
+                | "powered_rail"
+                | "detector_rail"
+                | "activator_rail"
+                | "pumpkin_stem"
+                | "melon_stem"
+                | "attached_pumpkin_stem"
+                | "attached_melon_stem"
+        );
+
+        !(non_solid
+            || name.ends_with("_sapling")
+            || name.ends_with("_tulip")
+            || name.ends_with("_button")
+            || name.ends_with("_pressure_plate")
+            || name.ends_with("_sign")
+            || name.ends_with("_banner")
+            || name.ends_with("_coral")
+            || name.ends_with("_coral_fan"))
+    }
+}
+
+/// Formatted like in the game commands, e.g. `minecraft:furnace[facing=north,lit=true]`
+impl fmt::Display for BlockState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        if !self.properties.is_empty() {
+            let props: Vec<String> = self
+                .properties
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect();
+            write!(f, "[{}]", props.join(","))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A box of blocks, all bounds being inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BoundingBox {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub min_z: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+    pub max_z: i32,
+}
+
+impl BoundingBox {
+    /// Create a box from two opposite corners, in any order
+    pub fn from_corners(a: &BlockPosition, b: &BlockPosition) -> BoundingBox {
+        BoundingBox {
+            min_x: a.x.min(b.x),
+            min_y: a.y.min(b.y),
+            min_z: a.z.min(b.z),
+            max_x: a.x.max(b.x),
+            max_y: a.y.max(b.y),
+            max_z: a.z.max(b.z),
+        }
+    }
+
+    /// Parse a `[min_x, min_y, min_z, max_x, max_y, max_z]` array, as stored in the NBT
+    pub fn from_int_array(values: &[i32]) -> Result<BoundingBox, String> {
+        match values {
+            [min_x, min_y, min_z, max_x, max_y, max_z] => Ok(BoundingBox {
+                min_x: *min_x,
+                min_y: *min_y,
+                min_z: *min_z,
+                max_x: *max_x,
+                max_y: *max_y,
+                max_z: *max_z,
+            }),
+            v => Err(format!("Bounding box must have 6 values. Got {v:?}")),
+        }
+    }
+
+    /// Smallest box containing both boxes
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            min_z: self.min_z.min(other.min_z),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+            max_z: self.max_z.max(other.max_z),
+        }
+    }
+
+    pub fn contains(&self, x: i32, y: i32, z: i32) -> bool {
+        x >= self.min_x
+            && x <= self.max_x
+            && y >= self.min_y
+            && y <= self.max_y
+            && z >= self.min_z
+            && z <= self.max_z
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min_x <= other.max_x
+            && self.max_x >= other.min_x
+            && self.min_y <= other.max_y
+            && self.max_y >= other.min_y
+            && self.min_z <= other.max_z
+            && self.max_z >= other.min_z
+    }
+
+    pub fn size_x(&self) -> i32 {
+        self.max_x - self.min_x + 1
+    }
+
+    pub fn size_y(&self) -> i32 {
+        self.max_y - self.min_y + 1
+    }
+
+    pub fn size_z(&self) -> i32 {
+        self.max_z - self.min_z + 1
+    }
 }
@@ -0,0 +1,141 @@
+use crate::{
+    chunks::Chunk,
+    worlds::{facing_range, World, NEIGHBOR_OFFSETS},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Chunks within `width` chunks of the borders of the regions of a world, shared by the threads
+/// rendering neighbouring regions. Unlike `World::read_border_chunks`, the border chunks of
+/// each region are only read and parsed once, or taken from the region if it is rendered
+/// first. They are dropped once every neighbouring region has used them
+pub struct BorderChunkCache {
+    width: i32,
+    regions: HashSet<(i32, i32)>,
+    entries: Mutex<HashMap<(i32, i32), Arc<BorderEntry>>>,
+}
+
+struct BorderEntry {
+    chunks: Mutex<Option<Arc<Vec<Chunk>>>>,
+    /// Neighbouring regions that have not used the chunks yet
+    users: AtomicUsize,
+}
+
+impl BorderChunkCache {
+    pub fn new(world: &World, width: i32) -> BorderChunkCache {
+        BorderChunkCache {
+            width: width.clamp(0, 32),
+            regions: world.regions.iter().map(|info| (info.x, info.z)).collect(),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Offsets of the existing regions around a region
+    fn neighbors(&self, x: i32, z: i32) -> impl Iterator<Item = (i32, i32)> + '_ {
+        NEIGHBOR_OFFSETS
+            .into_iter()
+            .filter(move |(dx, dz)| self.regions.contains(&(x + dx, z + dz)))
+    }
+
+    fn entry(&self, x: i32, z: i32) -> Arc<BorderEntry> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let entry = entries.entry((x, z)).or_insert_with(|| {
+            Arc::new(BorderEntry {
+                chunks: Mutex::new(None),
+                users: AtomicUsize::new(self.neighbors(x, z).count()),
+            })
+        });
+
+        Arc::clone(entry)
+    }
+
+    fn is_border(&self, local_x: i32, local_z: i32) -> bool {
+        [local_x, local_z]
+            .iter()
+            .any(|&v| v < self.width || v >= 32 - self.width)
+    }
+
+    /// Keep the border chunks of a parsed region for its neighbours, so they do not read it
+    pub fn add_region(&self, x: i32, z: i32, chunks: &[Chunk]) {
+        if self.neighbors(x, z).next().is_none() {
+            return;
+        }
+
+        let entry = self.entry(x, z);
+        let mut cached = entry.chunks.lock().unwrap();
+
+        if cached.is_none() && entry.users.load(Ordering::SeqCst) > 0 {
+            let border = chunks
+                .iter()
+                .filter(|c| {
+                    self.is_border(c.position.x.rem_euclid(32), c.position.z.rem_euclid(32))
+                })
+                .cloned()
+                .collect();
+            *cached = Some(Arc::new(border));
+        }
+    }
+
+    /// Chunks of the neighbouring regions within `width` chunks of the border of a region
+    pub fn border_chunks(&self, world: &World, x: i32, z: i32) -> io::Result<Vec<Chunk>> {
+        let mut chunks = Vec::new();
+
+        for (dx, dz) in self.neighbors(x, z) {
+            let entry = self.entry(x + dx, z + dz);
+            let mut cached = entry.chunks.lock().unwrap();
+
+            let border = match &*cached {
+                Some(border) => Arc::clone(border),
+                None => {
+                    let border = Arc::new(self.read_border(world, x + dx, z + dz)?);
+                    *cached = Some(Arc::clone(&border));
+                    border
+                }
+            };
+
+            chunks.extend(
+                border
+                    .iter()
+                    .filter(|c| {
+                        facing_range(dx, self.width).contains(&c.position.x.rem_euclid(32))
+                            && facing_range(dz, self.width).contains(&c.position.z.rem_euclid(32))
+                    })
+                    .cloned(),
+            );
+
+            if entry.users.fetch_sub(1, Ordering::SeqCst) == 1 {
+                *cached = None;
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    /// Parse the border chunks of a region file, without the others
+    fn read_border(&self, world: &World, x: i32, z: i32) -> io::Result<Vec<Chunk>> {
+        let Some(info) = world.region(x, z) else {
+            return Ok(Vec::new());
+        };
+        let region_file = world.read_region_file_raw(info)?;
+        let mut chunks = Vec::new();
+
+        for local_z in 0..32 {
+            for local_x in 0..32 {
+                if self.is_border(local_x, local_z) {
+                    if let Some(chunk) = region_file.get_chunk(local_x, local_z)? {
+                        chunks.push(chunk);
+                    }
+                }
+            }
+        }
+
+        Ok(chunks)
+    }
+}
@@ -2,13 +2,15 @@ use crate::{
     blocks::{Block, BlockPosition},
     dimensions::{get_dimension_height_offset, get_dimension_heights, Dimension},
     heightmaps::{decode_heightmap, Heightmap},
+    light::get_chunk_light_at_position,
     nbt::Compound,
     sections::{get_biome_at_position, get_block_at_position},
 };
 use fastnbt::Value;
+use serde::Serialize;
 use std::{collections::HashMap, error::Error};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ChunkPosition {
     pub x: i32,
     pub z: i32,
@@ -256,6 +258,13 @@ pub fn parse_chunk_surface(
                         false
                     };
 
+                    let light = get_chunk_light_at_position(
+                        &sections,
+                        local_x as usize,
+                        surface_y + 1,
+                        local_z as usize,
+                    )?;
+
                     highest_blocks.push(Block {
                         position: BlockPosition {
                             x: world_x,
@@ -265,6 +274,8 @@ pub fn parse_chunk_surface(
                         name: block_name,
                         depth,
                         snowy,
+                        block_light: light.block,
+                        sky_light: light.sky,
                     });
                 }
                 None => {
@@ -0,0 +1,309 @@
+use crate::{
+    blocks::{BlockPosition, BlockState},
+    chunks::{parse_chunk_sections, Chunk, ChunkPosition},
+    nbt::Compound,
+    regions::{RawChunk, RegionFile},
+    sections::{decode_block_indices, parse_block_palette},
+    worlds::World,
+};
+use fastnbt::Value;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkChange {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Kind of change of a single block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockChange {
+    /// Air replaced by a block
+    Placed,
+    /// A block replaced by air
+    Removed,
+    /// A block replaced by another block, or the same block with other properties
+    Replaced,
+}
+
+impl BlockChange {
+    pub fn between(old: &BlockState, new: &BlockState) -> Option<BlockChange> {
+        if old == new {
+            return None;
+        }
+
+        match (old.is_air(), new.is_air()) {
+            (true, true) => None,
+            (true, false) => Some(BlockChange::Placed),
+            (false, true) => Some(BlockChange::Removed),
+            (false, false) => Some(BlockChange::Replaced),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkDiff {
+    pub chunk: ChunkPosition,
+    pub change: ChunkChange,
+    pub changed_blocks: u64,
+    pub placed: u64,
+    pub removed: u64,
+    pub replaced: u64,
+    /// Difference of the number of blocks of each type, positive when there are more in the new world
+    pub block_deltas: BTreeMap<String, i64>,
+}
+
+impl ChunkDiff {
+    fn new(chunk: ChunkPosition, change: ChunkChange) -> ChunkDiff {
+        ChunkDiff {
+            chunk,
+            change,
+            changed_blocks: 0,
+            placed: 0,
+            removed: 0,
+            replaced: 0,
+            block_deltas: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, old: &BlockState, new: &BlockState, change: BlockChange) {
+        self.changed_blocks += 1;
+
+        match change {
+            BlockChange::Placed => self.placed += 1,
+            BlockChange::Removed => self.removed += 1,
+            BlockChange::Replaced => self.replaced += 1,
+        }
+
+        if old.name != new.name {
+            *self.block_deltas.entry(old.name.clone()).or_default() -= 1;
+            *self.block_deltas.entry(new.name.clone()).or_default() += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WorldDiff {
+    /// Chunks present in both worlds with the same header timestamp, or the same blocks and `LastUpdate`
+    pub unchanged_chunks: u64,
+    /// Chunks updated by the game without any block change, e.g. after entities moved
+    pub resaved_chunks: u64,
+    pub added_chunks: u64,
+    pub removed_chunks: u64,
+    pub modified_chunks: u64,
+    pub changed_blocks: u64,
+    pub block_deltas: BTreeMap<String, i64>,
+    pub chunks: Vec<ChunkDiff>,
+}
+
+impl WorldDiff {
+    fn add(&mut self, diff: ChunkDiff) {
+        match diff.change {
+            ChunkChange::Added => self.added_chunks += 1,
+            ChunkChange::Removed => self.removed_chunks += 1,
+            ChunkChange::Modified => self.modified_chunks += 1,
+        }
+
+        self.changed_blocks += diff.changed_blocks;
+        for (name, delta) in &diff.block_deltas {
+            *self.block_deltas.entry(name.clone()).or_default() += delta;
+        }
+        self.block_deltas.retain(|_, v| *v != 0);
+
+        self.chunks.push(diff);
+    }
+}
+
+struct SectionBlocks {
+    palette: Vec<BlockState>,
+    indices: Vec<usize>,
+}
+
+/// Decoded blocks of a section, None for missing or empty sections
+fn decode_section_blocks(section: Option<&&Compound>) -> Result<Option<SectionBlocks>, String> {
+    let Some(section) = section else {
+        return Ok(None);
+    };
+    if !section.contains_key("block_states") {
+        return Ok(None);
+    }
+
+    let palette = parse_block_palette(section)?;
+    let indices = decode_block_indices(section, palette.len())?;
+
+    Ok(Some(SectionBlocks { palette, indices }))
+}
+
+/// Call `f` for every block that differs between two versions of a chunk.
+/// A missing chunk or section is considered to be air
+pub fn for_each_block_change<F>(
+    old: Option<&Chunk>,
+    new: Option<&Chunk>,
+    mut f: F,
+) -> Result<(), String>
+where
+    F: FnMut(&BlockPosition, &BlockState, &BlockState, BlockChange),
+{
+    let (chunk_x, chunk_z) = match new.or(old) {
+        Some(chunk) => (chunk.position.x, chunk.position.z),
+        None => return Ok(()),
+    };
+
+    let old = chunk_root(old)?;
+    let new = chunk_root(new)?;
+    let old_sections = old
+        .map(parse_chunk_sections)
+        .transpose()?
+        .unwrap_or_default();
+    let new_sections = new
+        .map(parse_chunk_sections)
+        .transpose()?
+        .unwrap_or_default();
+
+    let section_ys: BTreeSet<i32> = old_sections
+        .keys()
+        .chain(new_sections.keys())
+        .copied()
+        .collect();
+    let air = BlockState::air();
+
+    for section_y in section_ys {
+        let old_section = old_sections.get(&section_y);
+        let new_section = new_sections.get(&section_y);
+
+        // Identical NBT means identical blocks, no need to decode it
+        if let (Some(a), Some(b)) = (old_section, new_section) {
+            if a.get("block_states") == b.get("block_states") {
+                continue;
+            }
+        }
+
+        let old_blocks = decode_section_blocks(old_section)?;
+        let new_blocks = decode_section_blocks(new_section)?;
+
+        for i in 0..4096 {
+            let old_block = old_blocks
+                .as_ref()
+                .map_or(&air, |s| &s.palette[s.indices[i]]);
+            let new_block = new_blocks
+                .as_ref()
+                .map_or(&air, |s| &s.palette[s.indices[i]]);
+
+            if let Some(change) = BlockChange::between(old_block, new_block) {
+                let position = BlockPosition {
+                    x: chunk_x * 16 + (i % 16) as i32,
+                    y: section_y * 16 + (i / 256) as i32,
+                    z: chunk_z * 16 + ((i / 16) % 16) as i32,
+                };
+                f(&position, old_block, new_block, change);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn chunk_root(chunk: Option<&Chunk>) -> Result<Option<&Compound>, String> {
+    match chunk.map(|c| &c.nbt) {
+        Some(Value::Compound(v)) => Ok(Some(v)),
+        Some(_) => Err("Root NBT is not a compound".into()),
+        None => Ok(None),
+    }
+}
+
+/// Compare the blocks of two versions of a chunk. Returns None if no block changed
+pub fn diff_chunks(old: Option<&Chunk>, new: Option<&Chunk>) -> Result<Option<ChunkDiff>, String> {
+    let (position, change) = match (old, new) {
+        (None, Some(c)) => (c.position.clone(), ChunkChange::Added),
+        (Some(c), None) => (c.position.clone(), ChunkChange::Removed),
+        (Some(c), Some(_)) => (c.position.clone(), ChunkChange::Modified),
+        (None, None) => return Ok(None),
+    };
+
+    let mut diff = ChunkDiff::new(position, change);
+    for_each_block_change(old, new, |_, old, new, change| diff.add(old, new, change))?;
+
+    if change == ChunkChange::Modified && diff.changed_blocks == 0 {
+        return Ok(None);
+    }
+
+    diff.block_deltas.retain(|_, v| *v != 0);
+
+    Ok(Some(diff))
+}
+
+/// Whether a chunk was not saved again since the old snapshot, from its header timestamp
+fn same_timestamp(old: &RawChunk, new: &RawChunk) -> bool {
+    old.timestamp == new.timestamp
+}
+
+/// Compare two versions of a region file chunk by chunk.
+/// Chunks with the same header timestamp are skipped without being decompressed. For the
+/// others the blocks are compared: a matching `LastUpdate` is not enough to skip a chunk,
+/// since external editors (including this crate) modify chunks without changing it
+pub fn diff_region_files(
+    old: Option<&RegionFile>,
+    new: Option<&RegionFile>,
+    diff: &mut WorldDiff,
+) -> Result<(), Box<dyn Error>> {
+    for i in 0..1024 {
+        let old_raw = old.and_then(|r| r.chunks[i].as_ref());
+        let new_raw = new.and_then(|r| r.chunks[i].as_ref());
+
+        if let (Some(a), Some(b)) = (old_raw, new_raw) {
+            if same_timestamp(a, b) {
+                diff.unchanged_chunks += 1;
+                continue;
+            }
+        }
+
+        let (x, z) = ((i % 32) as i32, (i / 32) as i32);
+        let old_chunk = old.map(|r| r.get_chunk(x, z)).transpose()?.flatten();
+        let new_chunk = new.map(|r| r.get_chunk(x, z)).transpose()?.flatten();
+
+        match diff_chunks(old_chunk.as_ref(), new_chunk.as_ref())? {
+            Some(chunk_diff) => diff.add(chunk_diff),
+            None => match (&old_chunk, &new_chunk) {
+                (Some(a), Some(b)) if a.last_update == b.last_update => diff.unchanged_chunks += 1,
+                (Some(_), Some(_)) => diff.resaved_chunks += 1,
+                _ => {}
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare every region file of two worlds
+pub fn diff_worlds(old: &World, new: &World) -> Result<WorldDiff, Box<dyn Error>> {
+    let regions: BTreeSet<(i32, i32)> = old
+        .regions
+        .iter()
+        .chain(&new.regions)
+        .map(|info| (info.x, info.z))
+        .collect();
+
+    let mut diff = WorldDiff::default();
+
+    for (x, z) in regions {
+        let old_file = old
+            .region(x, z)
+            .map(|info| old.read_region_file_raw(info))
+            .transpose()?;
+        let new_file = new
+            .region(x, z)
+            .map(|info| new.read_region_file_raw(info))
+            .transpose()?;
+
+        diff_region_files(old_file.as_ref(), new_file.as_ref(), &mut diff)?;
+    }
+
+    Ok(diff)
+}
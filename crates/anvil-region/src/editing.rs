@@ -0,0 +1,886 @@
+use crate::{
+    block_entities::{parse_block_entity, BlockEntity},
+    blocks::{BlockState, BoundingBox},
+    chunks::{Chunk, ChunkPosition},
+    dimensions::{get_dimension_heights, Dimension},
+    heightmaps::encode_heightmap,
+    nbt::Compound,
+    sections::{
+        calculate_biome_bits_per_entry, calculate_bits_per_entry, decode_biome_indices,
+        decode_block_indices, pack_palette_indices, parse_block_state, SECTION_BIOME_COUNT,
+        SECTION_BLOCK_COUNT,
+    },
+    worlds::World,
+};
+use fastnbt::{LongArray, Value};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+};
+
+/// A decoded block or biome container of a section, that can be modified then encoded back.
+/// Palette entries are kept as NBT: compounds for blocks, strings for biomes
+#[derive(Debug, Clone)]
+pub struct PalettedContainer {
+    pub palette: Vec<Value>,
+    pub indices: Vec<usize>,
+}
+
+impl PalettedContainer {
+    pub fn filled(value: Value, size: usize) -> PalettedContainer {
+        PalettedContainer {
+            palette: vec![value],
+            indices: vec![0; size],
+        }
+    }
+
+    /// Decode the `block_states` of a section
+    pub fn from_block_states(section: &Compound) -> Result<PalettedContainer, String> {
+        let palette = get_container_palette(section, "block_states")?;
+        let indices = decode_block_indices(section, palette.len())?;
+        Ok(PalettedContainer { palette, indices })
+    }
+
+    /// Decode the `biomes` of a section
+    pub fn from_biomes(section: &Compound) -> Result<PalettedContainer, String> {
+        let palette = get_container_palette(section, "biomes")?;
+        let indices = decode_biome_indices(section, palette.len())?;
+        Ok(PalettedContainer { palette, indices })
+    }
+
+    pub fn get(&self, index: usize) -> &Value {
+        &self.palette[self.indices[index]]
+    }
+
+    /// Set an entry, adding the value to the palette if it is not in it yet
+    pub fn set(&mut self, index: usize, value: Value) {
+        let palette_index = match self.palette.iter().position(|v| v == &value) {
+            Some(i) => i,
+            None => {
+                self.palette.push(value);
+                self.palette.len() - 1
+            }
+        };
+
+        self.indices[index] = palette_index;
+    }
+
+    /// Encode the container, removing the unused palette entries and repacking
+    /// the data with the bits per entry of the new palette size
+    pub fn to_nbt<F>(&self, bits_per_entry: F) -> Value
+    where
+        F: Fn(usize) -> usize,
+    {
+        let mut remap = vec![None; self.palette.len()];
+        let mut palette = Vec::new();
+        let mut indices = Vec::with_capacity(self.indices.len());
+
+        for &i in &self.indices {
+            let new_index = *remap[i].get_or_insert_with(|| {
+                palette.push(self.palette[i].clone());
+                palette.len() - 1
+            });
+            indices.push(new_index);
+        }
+
+        let mut container = Compound::new();
+
+        if palette.len() > 1 {
+            let data = pack_palette_indices(&indices, bits_per_entry(palette.len()));
+            container.insert("data".to_string(), Value::LongArray(LongArray::new(data)));
+        }
+
+        container.insert("palette".to_string(), Value::List(palette));
+
+        Value::Compound(container)
+    }
+}
+
+fn get_container_palette(section: &Compound, key: &str) -> Result<Vec<Value>, String> {
+    match section.get(key) {
+        Some(Value::Compound(container)) => match container.get("palette") {
+            Some(Value::List(v)) => Ok(v.clone()),
+            v => Err(format!(
+                "'{key}.palette' not found in section or not a List. Got {v:?}"
+            )),
+        },
+        v => Err(format!(
+            "'{key}' not found in section or not a Compound. Got {v:?}"
+        )),
+    }
+}
+
+pub fn block_state_to_nbt(block: &BlockState) -> Value {
+    let mut compound = Compound::new();
+    compound.insert("Name".to_string(), Value::String(block.name.clone()));
+
+    if !block.properties.is_empty() {
+        let properties = block
+            .properties
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect();
+        compound.insert("Properties".to_string(), Value::Compound(properties));
+    }
+
+    Value::Compound(compound)
+}
+
+struct EditedSection {
+    blocks: PalettedContainer,
+    biomes: PalettedContainer,
+    /// Only the modified sections are written back
+    modified: bool,
+}
+
+/// Y of a section of the chunk NBT
+fn section_y(section: &Compound) -> Option<i32> {
+    match section.get("Y") {
+        Some(Value::Byte(y)) => Some(*y as i32),
+        Some(Value::Int(y)) => Some(*y),
+        _ => None,
+    }
+}
+
+/// Modify the blocks and biomes of a chunk.
+/// Sections are decoded when first accessed, and the modified ones are written back into the
+/// chunk NBT by `save`, which also updates the heightmaps
+pub struct ChunkEditor<'a> {
+    chunk: &'a mut Chunk,
+    min_y: i32,
+    max_y: i32,
+    sections: BTreeMap<i32, EditedSection>,
+    modified: bool,
+}
+
+impl<'a> ChunkEditor<'a> {
+    pub fn new(chunk: &'a mut Chunk, dimension: &Dimension) -> ChunkEditor<'a> {
+        let (min_y, max_y) = get_dimension_heights(dimension);
+
+        ChunkEditor {
+            chunk,
+            min_y,
+            max_y,
+            sections: BTreeMap::new(),
+            modified: false,
+        }
+    }
+
+    pub fn position(&self) -> &ChunkPosition {
+        &self.chunk.position
+    }
+
+    /// The part of the world covered by this chunk
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox {
+            min_x: self.chunk.position.x * 16,
+            min_y: self.min_y,
+            min_z: self.chunk.position.z * 16,
+            max_x: self.chunk.position.x * 16 + 15,
+            max_y: self.max_y - 1,
+            max_z: self.chunk.position.z * 16 + 15,
+        }
+    }
+
+    fn root(&self) -> Result<&Compound, String> {
+        match &self.chunk.nbt {
+            Value::Compound(v) => Ok(v),
+            _ => Err("Root NBT is not a compound".into()),
+        }
+    }
+
+    /// Sections of the chunk NBT
+    fn stored_sections(&self) -> Result<impl Iterator<Item = (i32, &Compound)>, String> {
+        let sections = match self.root()?.get("sections") {
+            Some(Value::List(v)) => v,
+            _ => return Err("'sections' not found or not a list".into()),
+        };
+
+        Ok(sections.iter().filter_map(|section| match section {
+            Value::Compound(section) => section_y(section).map(|y| (y, section)),
+            _ => None,
+        }))
+    }
+
+    fn root_mut(&mut self) -> Result<&mut Compound, String> {
+        match &mut self.chunk.nbt {
+            Value::Compound(v) => Ok(v),
+            _ => Err("Root NBT is not a compound".into()),
+        }
+    }
+
+    fn section(&mut self, section_y: i32) -> Result<&mut EditedSection, String> {
+        if !self.sections.contains_key(&section_y) {
+            let section = self.decode_section(section_y)?;
+            self.sections.insert(section_y, section);
+        }
+
+        Ok(self.sections.get_mut(&section_y).unwrap())
+    }
+
+    fn decode_section(&self, section_y: i32) -> Result<EditedSection, String> {
+        let mut below_biome = None;
+
+        for (y, section) in self.stored_sections()? {
+            if y == section_y && section.contains_key("block_states") {
+                return Ok(EditedSection {
+                    blocks: PalettedContainer::from_block_states(section)?,
+                    biomes: PalettedContainer::from_biomes(section)?,
+                    modified: false,
+                });
+            }
+
+            if y == section_y - 1 {
+                below_biome = PalettedContainer::from_biomes(section)
+                    .ok()
+                    .map(|b| b.get(b.indices.len() - 1).clone());
+            }
+        }
+
+        // Missing sections are empty, they take the biome below them if they are modified
+        Ok(EditedSection {
+            blocks: PalettedContainer::filled(
+                block_state_to_nbt(&BlockState::air()),
+                SECTION_BLOCK_COUNT,
+            ),
+            biomes: PalettedContainer::filled(
+                below_biome.unwrap_or(Value::String("minecraft:plains".to_string())),
+                SECTION_BIOME_COUNT,
+            ),
+            modified: false,
+        })
+    }
+
+    fn check_position(&self, x: usize, y: i32, z: usize) -> Result<(), String> {
+        if x >= 16 || z >= 16 || y < self.min_y || y >= self.max_y {
+            return Err(format!(
+                "Position {x} {y} {z} is outside of the chunk (Y from {} to {})",
+                self.min_y,
+                self.max_y - 1
+            ));
+        }
+        Ok(())
+    }
+
+    /// Get a block, `x` and `z` being local to the chunk and `y` the world height
+    pub fn get_block(&mut self, x: usize, y: i32, z: usize) -> Result<BlockState, String> {
+        self.check_position(x, y, z)?;
+
+        let section = self.section(y.div_euclid(16))?;
+        let index = (y.rem_euclid(16) as usize * 16 + z) * 16 + x;
+
+        match section.blocks.get(index) {
+            Value::Compound(block) => parse_block_state(block),
+            v => Err(format!("Palette entry is not a Compound. Got {v:?}")),
+        }
+    }
+
+    /// Set a block, `x` and `z` being local to the chunk and `y` the world height
+    pub fn set_block(
+        &mut self,
+        x: usize,
+        y: i32,
+        z: usize,
+        block: &BlockState,
+    ) -> Result<(), String> {
+        self.check_position(x, y, z)?;
+
+        let section = self.section(y.div_euclid(16))?;
+        let index = (y.rem_euclid(16) as usize * 16 + z) * 16 + x;
+        section.blocks.set(index, block_state_to_nbt(block));
+        section.modified = true;
+
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Get the biome of the 4x4x4 cell containing the block
+    pub fn get_biome(&mut self, x: usize, y: i32, z: usize) -> Result<String, String> {
+        self.check_position(x, y, z)?;
+
+        let section = self.section(y.div_euclid(16))?;
+        let index = ((y.rem_euclid(16) as usize / 4) * 4 + z / 4) * 4 + x / 4;
+
+        match section.biomes.get(index) {
+            Value::String(name) => Ok(name.clone()),
+            v => Err(format!("Biome palette entry is not a String. Got {v:?}")),
+        }
+    }
+
+    /// Set the biome of the 4x4x4 cell containing the block
+    pub fn set_biome(&mut self, x: usize, y: i32, z: usize, biome: &str) -> Result<(), String> {
+        self.check_position(x, y, z)?;
+
+        let section = self.section(y.div_euclid(16))?;
+        let index = ((y.rem_euclid(16) as usize / 4) * 4 + z / 4) * 4 + x / 4;
+        section.biomes.set(index, Value::String(biome.to_string()));
+        section.modified = true;
+
+        self.modified = true;
+        Ok(())
+    }
+
+    fn block_entities_mut(&mut self) -> Result<&mut Vec<Value>, String> {
+        let root = self.root_mut()?;
+
+        if !root.contains_key("block_entities") {
+            root.insert("block_entities".to_string(), Value::List(Vec::new()));
+        }
+
+        match root.get_mut("block_entities") {
+            Some(Value::List(v)) => Ok(v),
+            v => Err(format!("'block_entities' is not a List. Got {v:?}")),
+        }
+    }
+
+    /// Add a block entity, replacing the one at the same position.
+    /// Its position is in world coordinates and must be inside the chunk
+    pub fn set_block_entity(&mut self, block_entity: &BlockEntity) -> Result<(), String> {
+        let p = &block_entity.position;
+        if !self.bounding_box().contains(p.x, p.y, p.z) {
+            return Err(format!(
+                "Block entity at {} {} {} is outside of the chunk",
+                p.x, p.y, p.z
+            ));
+        }
+
+        let (x, z) = (p.x.rem_euclid(16) as usize, p.z.rem_euclid(16) as usize);
+        self.remove_block_entity(x, p.y, z)?;
+        self.block_entities_mut()?.push(block_entity.to_nbt());
+
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Remove the block entity of a block, `x` and `z` being local to the chunk and `y` the world height.
+    /// Returns false if there was none
+    pub fn remove_block_entity(&mut self, x: usize, y: i32, z: usize) -> Result<bool, String> {
+        self.check_position(x, y, z)?;
+
+        let world_x = self.chunk.position.x * 16 + x as i32;
+        let world_z = self.chunk.position.z * 16 + z as i32;
+
+        let removed = self.remove_block_entities(|be| {
+            be.position.x == world_x && be.position.y == y && be.position.z == world_z
+        })?;
+
+        Ok(removed > 0)
+    }
+
+    /// Remove the block entities matching the predicate. Returns how many were removed
+    pub fn remove_block_entities<F>(&mut self, predicate: F) -> Result<usize, String>
+    where
+        F: Fn(&BlockEntity) -> bool,
+    {
+        let block_entities = self.block_entities_mut()?;
+        let count = block_entities.len();
+
+        let mut error = None;
+        block_entities.retain(|v| match v {
+            Value::Compound(compound) => match parse_block_entity(compound) {
+                Ok(be) => !predicate(&be),
+                Err(e) => {
+                    error = Some(e);
+                    true
+                }
+            },
+            _ => true,
+        });
+
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        let removed = count - block_entities.len();
+        if removed > 0 {
+            self.modified = true;
+        }
+
+        Ok(removed)
+    }
+
+    /// Write the modified sections back into the chunk and update its heightmaps.
+    /// The light of the chunk is marked as outdated so the game recomputes it.
+    /// Returns false if nothing was modified
+    pub fn save(mut self) -> Result<bool, String> {
+        if !self.modified {
+            return Ok(false);
+        }
+
+        let heightmaps = self.compute_heightmaps()?;
+        let edited = std::mem::take(&mut self.sections);
+        let root = self.root_mut()?;
+
+        let sections = match root.get_mut("sections") {
+            Some(Value::List(v)) => v,
+            _ => return Err("'sections' not found or not a list".into()),
+        };
+
+        let mut existing: HashMap<i32, usize> = HashMap::new();
+        for (i, section) in sections.iter().enumerate() {
+            if let Some(y) = match section {
+                Value::Compound(section) => section_y(section),
+                _ => None,
+            } {
+                existing.insert(y, i);
+            }
+        }
+
+        for (section_y, section) in edited.into_iter().filter(|(_, s)| s.modified) {
+            let block_states = section.blocks.to_nbt(calculate_bits_per_entry);
+            let biomes = section.biomes.to_nbt(calculate_biome_bits_per_entry);
+
+            match existing.get(&section_y) {
+                Some(&i) => {
+                    if let Value::Compound(compound) = &mut sections[i] {
+                        compound.insert("block_states".to_string(), block_states);
+                        compound.insert("biomes".to_string(), biomes);
+                    }
+                }
+                None => {
+                    let mut compound = Compound::new();
+                    compound.insert("Y".to_string(), Value::Byte(section_y as i8));
+                    compound.insert("block_states".to_string(), block_states);
+                    compound.insert("biomes".to_string(), biomes);
+                    sections.push(Value::Compound(compound));
+                }
+            }
+        }
+
+        let mut heightmaps_compound = match root.remove("Heightmaps") {
+            Some(Value::Compound(v)) => v,
+            _ => Compound::new(),
+        };
+        for (key, heights) in heightmaps {
+            heightmaps_compound.insert(
+                key.to_string(),
+                Value::LongArray(LongArray::new(encode_heightmap(&heights))),
+            );
+        }
+        root.insert(
+            "Heightmaps".to_string(),
+            Value::Compound(heightmaps_compound),
+        );
+        root.insert("isLightOn".to_string(), Value::Byte(0));
+
+        Ok(true)
+    }
+
+    /// Compute the heightmaps stored in full chunks, from the decoded sections and the
+    /// stored ones that were not accessed. Missing sections are air
+    fn compute_heightmaps(&self) -> Result<[(&'static str, Vec<u16>); 4], String> {
+        let mut blocks: BTreeMap<i32, Cow<PalettedContainer>> = BTreeMap::new();
+        for (section_y, section) in self.stored_sections()? {
+            if !self.sections.contains_key(&section_y) && section.contains_key("block_states") {
+                let container = PalettedContainer::from_block_states(section)?;
+                blocks.insert(section_y, Cow::Owned(container));
+            }
+        }
+        for (&section_y, section) in &self.sections {
+            blocks.insert(section_y, Cow::Borrowed(&section.blocks));
+        }
+
+        let mut world_surface = vec![0u16; 256];
+        let mut ocean_floor = vec![0u16; 256];
+        let mut motion_blocking = vec![0u16; 256];
+        let mut motion_blocking_no_leaves = vec![0u16; 256];
+
+        // Classify each palette entry once
+        let mut cache: HashMap<(i32, usize), [bool; 4]> = HashMap::new();
+
+        for (&section_y, section_blocks) in blocks.iter().rev() {
+            for (palette_index, entry) in section_blocks.palette.iter().enumerate() {
+                let block = match entry {
+                    Value::Compound(block) => parse_block_state(block)?,
+                    v => return Err(format!("Palette entry is not a Compound. Got {v:?}")),
+                };
+                let motion = block.blocks_motion() || block.has_fluid();
+                cache.insert(
+                    (section_y, palette_index),
+                    [
+                        !block.is_air(),
+                        block.blocks_motion(),
+                        motion,
+                        motion && !block.is_leaves(),
+                    ],
+                );
+            }
+
+            for local_y in (0..16).rev() {
+                let y = section_y * 16 + local_y;
+                if y < self.min_y || y >= self.max_y {
+                    continue;
+                }
+                // Heights are stored as 1 + the Y of the highest block, from the bottom of the world
+                let height = (y - self.min_y + 1) as u16;
+
+                for column in 0..256 {
+                    let palette_index = section_blocks.indices[local_y as usize * 256 + column];
+                    let flags = cache[&(section_y, palette_index)];
+
+                    for (heights, flag) in [
+                        (&mut world_surface, flags[0]),
+                        (&mut ocean_floor, flags[1]),
+                        (&mut motion_blocking, flags[2]),
+                        (&mut motion_blocking_no_leaves, flags[3]),
+                    ] {
+                        if flag && heights[column] == 0 {
+                            heights[column] = height;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok([
+            ("WORLD_SURFACE", world_surface),
+            ("OCEAN_FLOOR", ocean_floor),
+            ("MOTION_BLOCKING", motion_blocking),
+            ("MOTION_BLOCKING_NO_LEAVES", motion_blocking_no_leaves),
+        ])
+    }
+}
+
+/// Set every block of the area inside the chunk. Returns the number of blocks changed
+pub fn fill_blocks(
+    editor: &mut ChunkEditor,
+    area: &BoundingBox,
+    block: &BlockState,
+) -> Result<u64, String> {
+    replace_blocks(editor, area, |_| true, block)
+}
+
+/// Replace the blocks matching the predicate in the area inside the chunk.
+/// Returns the number of blocks changed
+pub fn replace_blocks<F>(
+    editor: &mut ChunkEditor,
+    area: &BoundingBox,
+    predicate: F,
+    block: &BlockState,
+) -> Result<u64, String>
+where
+    F: Fn(&BlockState) -> bool,
+{
+    let chunk_box = editor.bounding_box();
+    if !chunk_box.intersects(area) {
+        return Ok(0);
+    }
+
+    let mut count = 0;
+
+    for y in area.min_y.max(chunk_box.min_y)..=area.max_y.min(chunk_box.max_y) {
+        for z in area.min_z.max(chunk_box.min_z)..=area.max_z.min(chunk_box.max_z) {
+            for x in area.min_x.max(chunk_box.min_x)..=area.max_x.min(chunk_box.max_x) {
+                let local_x = (x - chunk_box.min_x) as usize;
+                let local_z = (z - chunk_box.min_z) as usize;
+
+                let current = editor.get_block(local_x, y, local_z)?;
+                if &current == block || !predicate(&current) {
+                    continue;
+                }
+
+                editor.set_block(local_x, y, local_z, block)?;
+                count += 1;
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// Set the biome of the 4x4x4 cells intersecting the area inside the chunk.
+/// If `from` is set, only the cells of this biome are changed.
+/// Returns the number of cells changed
+pub fn replace_biomes(
+    editor: &mut ChunkEditor,
+    area: &BoundingBox,
+    from: Option<&str>,
+    biome: &str,
+) -> Result<u64, String> {
+    let chunk_box = editor.bounding_box();
+    if !chunk_box.intersects(area) {
+        return Ok(0);
+    }
+
+    let mut count = 0;
+
+    // Biome cells are aligned on multiples of 4, so the first block of a cell is enough
+    let min_y = area.min_y.max(chunk_box.min_y).div_euclid(4) * 4;
+    let min_z = area.min_z.max(chunk_box.min_z).div_euclid(4) * 4;
+    let min_x = area.min_x.max(chunk_box.min_x).div_euclid(4) * 4;
+
+    for y in (min_y..=area.max_y.min(chunk_box.max_y)).step_by(4) {
+        for z in (min_z..=area.max_z.min(chunk_box.max_z)).step_by(4) {
+            for x in (min_x..=area.max_x.min(chunk_box.max_x)).step_by(4) {
+                let local_x = (x - chunk_box.min_x) as usize;
+                let local_z = (z - chunk_box.min_z) as usize;
+
+                let current = editor.get_biome(local_x, y, local_z)?;
+                if current == biome || from.is_some_and(|from| current != from) {
+                    continue;
+                }
+
+                editor.set_biome(local_x, y, local_z, biome)?;
+                count += 1;
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// Edit every existing chunk of the world intersecting the area, then write the
+/// modified region files. The region files are only replaced once all of them are edited
+/// and written next to the originals, so an error while editing or writing them leaves the
+/// world unchanged. Each file is then renamed over its original, and a failed rename can
+/// leave the world partly written. `timestamp` is stored in the region headers, in seconds.
+/// Returns the number of chunks modified
+pub fn edit_world_area<F>(
+    world: &World,
+    dimension: &Dimension,
+    area: &BoundingBox,
+    timestamp: u32,
+    mut edit: F,
+) -> Result<usize, Box<dyn Error>>
+where
+    F: FnMut(&mut ChunkEditor) -> Result<(), String>,
+{
+    let mut modified_chunks = 0;
+    let mut staged = Vec::new();
+
+    let min_chunk_x = area.min_x.div_euclid(16);
+    let max_chunk_x = area.max_x.div_euclid(16);
+    let min_chunk_z = area.min_z.div_euclid(16);
+    let max_chunk_z = area.max_z.div_euclid(16);
+
+    for region_x in min_chunk_x.div_euclid(32)..=max_chunk_x.div_euclid(32) {
+        for region_z in min_chunk_z.div_euclid(32)..=max_chunk_z.div_euclid(32) {
+            let Some(info) = world.region(region_x, region_z) else {
+                continue;
+            };
+
+            let mut region_file = world.read_region_file_raw(info)?;
+            let mut region_modified = false;
+
+            for chunk_x in min_chunk_x.max(region_x * 32)..=max_chunk_x.min(region_x * 32 + 31) {
+                for chunk_z in min_chunk_z.max(region_z * 32)..=max_chunk_z.min(region_z * 32 + 31)
+                {
+                    let Some(mut chunk) = region_file.get_chunk(chunk_x, chunk_z)? else {
+                        continue;
+                    };
+
+                    let mut editor = ChunkEditor::new(&mut chunk, dimension);
+                    edit(&mut editor)?;
+
+                    if editor.save()? {
+                        region_file.set_chunk(&chunk, timestamp)?;
+                        region_modified = true;
+                        modified_chunks += 1;
+                    }
+                }
+            }
+
+            if region_modified {
+                staged.push(world.stage_region_file(info, &region_file)?);
+            }
+        }
+    }
+
+    for file in staged {
+        file.commit()?;
+    }
+
+    Ok(modified_chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heightmaps::decode_heightmap;
+
+    fn section(y: i8, block: &str) -> Value {
+        let mut section = Compound::new();
+        section.insert("Y".to_string(), Value::Byte(y));
+        section.insert(
+            "block_states".to_string(),
+            PalettedContainer::filled(block_state_to_nbt(&BlockState::new(block)), 4096)
+                .to_nbt(calculate_bits_per_entry),
+        );
+        section.insert(
+            "biomes".to_string(),
+            PalettedContainer::filled(Value::String("minecraft:plains".to_string()), 64)
+                .to_nbt(calculate_biome_bits_per_entry),
+        );
+        Value::Compound(section)
+    }
+
+    fn chunk(sections: Vec<Value>) -> Chunk {
+        let mut root = Compound::new();
+        root.insert("sections".to_string(), Value::List(sections));
+
+        Chunk {
+            data_version: 3953,
+            last_update: 0,
+            inhabited_time: 0,
+            position: ChunkPosition { x: 0, z: 0 },
+            nbt: Value::Compound(root),
+        }
+    }
+
+    fn sections(chunk: &Chunk) -> &Vec<Value> {
+        match &chunk.nbt {
+            Value::Compound(root) => match root.get("sections") {
+                Some(Value::List(sections)) => sections,
+                _ => panic!("No sections"),
+            },
+            _ => panic!("Root NBT is not a compound"),
+        }
+    }
+
+    /// Encode the container into a section, decode it back and compare every entry.
+    /// Returns the length of the packed data
+    fn round_trip(container: &PalettedContainer, key: &str) -> usize {
+        let mut section = Compound::new();
+        let (bits_per_entry, size): (fn(usize) -> usize, usize) = match key {
+            "block_states" => (calculate_bits_per_entry, SECTION_BLOCK_COUNT),
+            _ => (calculate_biome_bits_per_entry, SECTION_BIOME_COUNT),
+        };
+        section.insert(key.to_string(), container.to_nbt(bits_per_entry));
+
+        let decoded = match key {
+            "block_states" => PalettedContainer::from_block_states(&section),
+            _ => PalettedContainer::from_biomes(&section),
+        }
+        .unwrap();
+
+        for i in 0..size {
+            assert_eq!(decoded.get(i), container.get(i), "entry {i} of {key}");
+        }
+
+        let used = container
+            .indices
+            .iter()
+            .collect::<std::collections::HashSet<_>>()
+            .len();
+        assert_eq!(decoded.palette.len(), used);
+
+        match &section[key] {
+            Value::Compound(c) => match c.get("data") {
+                Some(Value::LongArray(data)) => data.len(),
+                _ => 0,
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn block(i: usize) -> Value {
+        block_state_to_nbt(&BlockState::new(&format!("test:block_{i}")))
+    }
+
+    #[test]
+    fn block_container_grows_and_shrinks() {
+        let mut container =
+            PalettedContainer::filled(block_state_to_nbt(&BlockState::air()), SECTION_BLOCK_COUNT);
+        assert_eq!(round_trip(&container, "block_states"), 0);
+
+        // Palette sizes at the limits of each bits per entry, from 4 to 9 bits
+        let mut size = 1;
+        for (entries, bits) in [
+            (2, 4),
+            (16, 4),
+            (17, 5),
+            (33, 6),
+            (65, 7),
+            (129, 8),
+            (257, 9),
+        ] {
+            while size < entries {
+                container.set(size * 13 % SECTION_BLOCK_COUNT, block(size));
+                size += 1;
+            }
+
+            let longs = SECTION_BLOCK_COUNT.div_ceil(64 / bits);
+            assert_eq!(
+                round_trip(&container, "block_states"),
+                longs,
+                "{entries} entries"
+            );
+        }
+
+        // Overwritten entries are removed from the palette, and the data is packed again
+        for i in 0..SECTION_BLOCK_COUNT {
+            container.set(i, block(i % 3));
+        }
+        assert!(container.palette.len() > 256);
+        assert_eq!(round_trip(&container, "block_states"), 256);
+
+        for i in 0..SECTION_BLOCK_COUNT {
+            container.set(i, block(1));
+        }
+        assert_eq!(round_trip(&container, "block_states"), 0);
+    }
+
+    #[test]
+    fn biome_container_round_trip() {
+        let biome = |i: usize| Value::String(format!("test:biome_{i}"));
+
+        // 1 entry has no data, then 1, 2 and 3 bits per entry
+        for (entries, longs) in [(1, 0), (2, 1), (3, 2), (4, 2), (5, 4), (8, 4)] {
+            let mut container = PalettedContainer::filled(biome(0), SECTION_BIOME_COUNT);
+            for i in 0..SECTION_BIOME_COUNT {
+                container.set(i, biome(i * 7 % entries));
+            }
+
+            assert_eq!(round_trip(&container, "biomes"), longs, "{entries} entries");
+        }
+    }
+
+    #[test]
+    fn save_only_writes_modified_sections() {
+        let mut chunk = chunk(vec![
+            section(-4, "minecraft:stone"),
+            section(0, "minecraft:stone"),
+            section(1, "minecraft:air"),
+        ]);
+        let original = sections(&chunk).clone();
+
+        let mut editor = ChunkEditor::new(&mut chunk, &Dimension::Overworld);
+        editor
+            .set_block(1, 5, 2, &BlockState::new("minecraft:dirt"))
+            .unwrap();
+        assert!(editor.save().unwrap());
+
+        let saved = sections(&chunk);
+        assert_eq!(saved.len(), 3);
+        assert_eq!(saved[0], original[0]);
+        assert_ne!(saved[1], original[1]);
+        assert_eq!(saved[2], original[2]);
+
+        // The heightmaps still see the sections that were not accessed
+        let Value::Compound(root) = &chunk.nbt else {
+            unreachable!()
+        };
+        let Some(Value::Compound(heightmaps)) = root.get("Heightmaps") else {
+            panic!("No heightmaps");
+        };
+        let Some(Value::LongArray(world_surface)) = heightmaps.get("WORLD_SURFACE") else {
+            panic!("No WORLD_SURFACE heightmap");
+        };
+        let heights = decode_heightmap(world_surface).unwrap();
+        assert!(heights.iter().all(|&h| h == 16 + 64));
+    }
+
+    #[test]
+    fn reading_does_not_modify_the_chunk() {
+        let mut chunk = chunk(vec![section(0, "minecraft:stone")]);
+        let original = chunk.nbt.clone();
+
+        let mut editor = ChunkEditor::new(&mut chunk, &Dimension::Overworld);
+        assert_eq!(editor.get_block(0, 100, 0).unwrap(), BlockState::air());
+        assert_eq!(editor.get_biome(0, 100, 0).unwrap(), "minecraft:plains");
+        assert!(!editor.save().unwrap());
+
+        assert_eq!(chunk.nbt, original);
+    }
+}
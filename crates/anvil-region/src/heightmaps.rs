@@ -23,3 +23,18 @@ pub fn decode_heightmap(packed_data: &[i64]) -> Result<Heightmap, String> {
 
     Ok(heights)
 }
+
+/// Pack a heightmap into longs, the inverse of `decode_heightmap`
+pub fn encode_heightmap(heights: &[u16]) -> Vec<i64> {
+    let mut packed_data = vec![0u64; HM_LENGTH.div_ceil(HM_VALUES_PER_LONG)];
+
+    for (i, &height) in heights.iter().take(HM_LENGTH).enumerate() {
+        let long_index = i / HM_VALUES_PER_LONG;
+        let value_index = i % HM_VALUES_PER_LONG;
+
+        let mask = (1u64 << HM_BITS_PER_VALUE) - 1;
+        packed_data[long_index] |= (height as u64 & mask) << (value_index * HM_BITS_PER_VALUE);
+    }
+
+    packed_data.into_iter().map(|v| v as i64).collect()
+}
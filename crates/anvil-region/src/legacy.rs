@@ -0,0 +1,426 @@
+use crate::{
+    blocks::BlockState,
+    editing::{block_state_to_nbt, PalettedContainer},
+    heightmaps::encode_heightmap,
+    nbt::Compound,
+    sections::{
+        calculate_biome_bits_per_entry, calculate_bits_per_entry, SECTION_BIOME_COUNT,
+        SECTION_BLOCK_COUNT,
+    },
+};
+use fastnbt::{ByteArray, LongArray, Value};
+use std::collections::HashMap;
+
+/// Height of McRegion chunks
+pub const LEGACY_HEIGHT: usize = 128;
+/// McRegion chunks did not store biomes
+pub const LEGACY_BIOME: &str = "minecraft:plains";
+/// The converted chunks follow the layout of the overworld, which starts at Y=-64
+const MIN_Y: i32 = -64;
+
+const WOOD_TYPES: [&str; 4] = ["oak", "spruce", "birch", "jungle"];
+const COLORS: [&str; 16] = [
+    "white",
+    "orange",
+    "magenta",
+    "light_blue",
+    "yellow",
+    "lime",
+    "pink",
+    "gray",
+    "light_gray",
+    "cyan",
+    "purple",
+    "blue",
+    "brown",
+    "green",
+    "red",
+    "black",
+];
+const SLAB_TYPES: [&str; 8] = [
+    "smooth_stone",
+    "sandstone",
+    "oak",
+    "cobblestone",
+    "brick",
+    "stone_brick",
+    "smooth_stone",
+    "smooth_stone",
+];
+
+/// Whether the chunk uses the McRegion layout, with a `Level.Blocks` byte array
+pub fn is_legacy_chunk(root: &Compound) -> bool {
+    matches!(
+        root.get("Level"),
+        Some(Value::Compound(level)) if matches!(level.get("Blocks"), Some(Value::ByteArray(_)))
+    )
+}
+
+/// Modern block state of a numeric block id and its data value, as saved before 1.13
+pub fn legacy_block_state(id: u8, data: u8) -> BlockState {
+    let name = match id {
+        0 => "air".to_string(),
+        1 => "stone".to_string(),
+        2 => "grass_block".to_string(),
+        3 => "dirt".to_string(),
+        4 => "cobblestone".to_string(),
+        5 => format!("{}_planks", WOOD_TYPES[(data & 3) as usize]),
+        6 => format!("{}_sapling", WOOD_TYPES[(data & 3) as usize]),
+        7 => "bedrock".to_string(),
+        8 | 9 => "water".to_string(),
+        10 | 11 => "lava".to_string(),
+        12 => "sand".to_string(),
+        13 => "gravel".to_string(),
+        14 => "gold_ore".to_string(),
+        15 => "iron_ore".to_string(),
+        16 => "coal_ore".to_string(),
+        17 => format!("{}_log", WOOD_TYPES[(data & 3) as usize]),
+        18 => format!("{}_leaves", WOOD_TYPES[(data & 3) as usize]),
+        19 => "sponge".to_string(),
+        20 => "glass".to_string(),
+        21 => "lapis_ore".to_string(),
+        22 => "lapis_block".to_string(),
+        23 => "dispenser".to_string(),
+        24 => match data {
+            1 => "chiseled_sandstone",
+            2 => "cut_sandstone",
+            _ => "sandstone",
+        }
+        .to_string(),
+        25 => "note_block".to_string(),
+        26 => "red_bed".to_string(),
+        27 => "powered_rail".to_string(),
+        28 => "detector_rail".to_string(),
+        29 => "sticky_piston".to_string(),
+        30 => "cobweb".to_string(),
+        31 => match data {
+            0 => "dead_bush",
+            2 => "fern",
+            _ => "short_grass",
+        }
+        .to_string(),
+        32 => "dead_bush".to_string(),
+        33 => "piston".to_string(),
+        34 => "piston_head".to_string(),
+        35 => format!("{}_wool", COLORS[(data & 15) as usize]),
+        36 => "moving_piston".to_string(),
+        37 => "dandelion".to_string(),
+        38 => "poppy".to_string(),
+        39 => "brown_mushroom".to_string(),
+        40 => "red_mushroom".to_string(),
+        41 => "gold_block".to_string(),
+        42 => "iron_block".to_string(),
+        43 | 44 => format!("{}_slab", SLAB_TYPES[(data & 7) as usize]),
+        45 => "bricks".to_string(),
+        46 => "tnt".to_string(),
+        47 => "bookshelf".to_string(),
+        48 => "mossy_cobblestone".to_string(),
+        49 => "obsidian".to_string(),
+        50 if data == 5 || data == 0 => "torch".to_string(),
+        50 => "wall_torch".to_string(),
+        51 => "fire".to_string(),
+        52 => "spawner".to_string(),
+        53 => "oak_stairs".to_string(),
+        54 | 95 => "chest".to_string(),
+        55 => "redstone_wire".to_string(),
+        56 => "diamond_ore".to_string(),
+        57 => "diamond_block".to_string(),
+        58 => "crafting_table".to_string(),
+        59 => "wheat".to_string(),
+        60 => "farmland".to_string(),
+        61 | 62 => "furnace".to_string(),
+        63 => "oak_sign".to_string(),
+        64 => "oak_door".to_string(),
+        65 => "ladder".to_string(),
+        66 => "rail".to_string(),
+        67 => "cobblestone_stairs".to_string(),
+        68 => "oak_wall_sign".to_string(),
+        69 => "lever".to_string(),
+        70 => "stone_pressure_plate".to_string(),
+        71 => "iron_door".to_string(),
+        72 => "oak_pressure_plate".to_string(),
+        73 | 74 => "redstone_ore".to_string(),
+        75 | 76 if data == 5 || data == 0 => "redstone_torch".to_string(),
+        75 | 76 => "redstone_wall_torch".to_string(),
+        77 => "stone_button".to_string(),
+        78 => "snow".to_string(),
+        79 => "ice".to_string(),
+        80 => "snow_block".to_string(),
+        81 => "cactus".to_string(),
+        82 => "clay".to_string(),
+        83 => "sugar_cane".to_string(),
+        84 => "jukebox".to_string(),
+        85 => "oak_fence".to_string(),
+        86 => "carved_pumpkin".to_string(),
+        87 => "netherrack".to_string(),
+        88 => "soul_sand".to_string(),
+        89 => "glowstone".to_string(),
+        90 => "nether_portal".to_string(),
+        91 => "jack_o_lantern".to_string(),
+        92 => "cake".to_string(),
+        93 | 94 => "repeater".to_string(),
+        96 => "oak_trapdoor".to_string(),
+        97 => match data {
+            1 => "infested_cobblestone",
+            2 => "infested_stone_bricks",
+            _ => "infested_stone",
+        }
+        .to_string(),
+        98 => match data {
+            1 => "mossy_stone_bricks",
+            2 => "cracked_stone_bricks",
+            3 => "chiseled_stone_bricks",
+            _ => "stone_bricks",
+        }
+        .to_string(),
+        99 => "brown_mushroom_block".to_string(),
+        100 => "red_mushroom_block".to_string(),
+        101 => "iron_bars".to_string(),
+        102 => "glass_pane".to_string(),
+        103 => "melon".to_string(),
+        104 => "pumpkin_stem".to_string(),
+        105 => "melon_stem".to_string(),
+        106 => "vine".to_string(),
+        107 => "oak_fence_gate".to_string(),
+        108 => "brick_stairs".to_string(),
+        109 => "stone_brick_stairs".to_string(),
+        110 => "mycelium".to_string(),
+        111 => "lily_pad".to_string(),
+        112 => "nether_bricks".to_string(),
+        113 => "nether_brick_fence".to_string(),
+        114 => "nether_brick_stairs".to_string(),
+        115 => "nether_wart".to_string(),
+        116 => "enchanting_table".to_string(),
+        117 => "brewing_stand".to_string(),
+        118 => "cauldron".to_string(),
+        119 => "end_portal".to_string(),
+        120 => "end_portal_frame".to_string(),
+        121 => "end_stone".to_string(),
+        122 => "dragon_egg".to_string(),
+        // Unknown ids are kept visible, e.g. blocks added by mods
+        id => format!("legacy_block_{id}"),
+    };
+
+    let mut block = BlockState::new(&format!("minecraft:{name}"));
+    let properties = &mut block.properties;
+
+    match id {
+        8..=11 => {
+            properties.insert("level".to_string(), (data & 15).to_string());
+        }
+        17 => {
+            properties.insert("axis".to_string(), "y".to_string());
+        }
+        43 => {
+            properties.insert("type".to_string(), "double".to_string());
+        }
+        44 => {
+            let half = if data & 8 != 0 { "top" } else { "bottom" };
+            properties.insert("type".to_string(), half.to_string());
+        }
+        59 => {
+            properties.insert("age".to_string(), (data & 7).to_string());
+        }
+        62 | 74 | 76 => {
+            properties.insert("lit".to_string(), "true".to_string());
+        }
+        // Redstone torches are lit by default
+        75 => {
+            properties.insert("lit".to_string(), "false".to_string());
+        }
+        94 => {
+            properties.insert("powered".to_string(), "true".to_string());
+        }
+        _ => {}
+    }
+
+    block
+}
+
+fn get_byte_array<'a>(level: &'a Compound, key: &str, length: usize) -> Result<&'a [i8], String> {
+    match level.get(key) {
+        Some(Value::ByteArray(v)) if v.len() >= length => Ok(v),
+        v => Err(format!(
+            "'Level.{key}' not found or not a ByteArray of {length} bytes. Got {v:?}"
+        )),
+    }
+}
+
+/// Read a nibble array stored in XZY order, like the `Data` and light arrays of McRegion chunks
+fn get_legacy_nibble(array: &[i8], index: usize) -> u8 {
+    let byte = array[index / 2] as u8;
+
+    if index & 1 == 0 {
+        byte & 0x0F
+    } else {
+        byte >> 4
+    }
+}
+
+fn get_int(level: &Compound, key: &str) -> Result<i32, String> {
+    match level.get(key) {
+        Some(Value::Int(v)) => Ok(*v),
+        v => Err(format!("'Level.{key}' not found or not an Int. Got {v:?}")),
+    }
+}
+
+/// Convert a McRegion chunk into the NBT of a modern chunk, with sections, palettes and
+/// heightmaps, so it can be read like any other chunk. The blocks keep their Y level and
+/// the heightmaps follow the layout of the overworld
+pub fn convert_legacy_chunk(root: &Compound) -> Result<Value, String> {
+    let level = match root.get("Level") {
+        Some(Value::Compound(v)) => v,
+        v => return Err(format!("'Level' not found or not a Compound. Got {v:?}")),
+    };
+
+    let block_count = 16 * 16 * LEGACY_HEIGHT;
+    let blocks = get_byte_array(level, "Blocks", block_count)?;
+    let data = get_byte_array(level, "Data", block_count / 2)?;
+    let block_light = get_byte_array(level, "BlockLight", block_count / 2).ok();
+    let sky_light = get_byte_array(level, "SkyLight", block_count / 2).ok();
+
+    let x_pos = get_int(level, "xPos")?;
+    let z_pos = get_int(level, "zPos")?;
+    let last_update = match level.get("LastUpdate") {
+        Some(Value::Long(v)) => *v,
+        _ => 0,
+    };
+
+    // Legacy index of a position
+    let legacy_index =
+        |x: usize, y: usize, z: usize| y + z * LEGACY_HEIGHT + x * LEGACY_HEIGHT * 16;
+
+    let mut states: HashMap<(u8, u8), BlockState> = HashMap::new();
+    let mut sections = Vec::new();
+    let mut motion_blocking = vec![0u16; 256];
+    let mut ocean_floor = vec![0u16; 256];
+
+    let air = block_state_to_nbt(&BlockState::air());
+    let biomes =
+        PalettedContainer::filled(Value::String(LEGACY_BIOME.to_string()), SECTION_BIOME_COUNT)
+            .to_nbt(calculate_biome_bits_per_entry);
+
+    // Empty sections below, so that empty columns can still be read
+    for section_y in MIN_Y.div_euclid(16)..0 {
+        let mut section = Compound::new();
+        section.insert("Y".to_string(), Value::Byte(section_y as i8));
+        section.insert(
+            "block_states".to_string(),
+            PalettedContainer::filled(air.clone(), SECTION_BLOCK_COUNT)
+                .to_nbt(calculate_bits_per_entry),
+        );
+        section.insert("biomes".to_string(), biomes.clone());
+        sections.push(Value::Compound(section));
+    }
+
+    for section_y in 0..(LEGACY_HEIGHT / 16) {
+        let mut container = PalettedContainer::filled(air.clone(), SECTION_BLOCK_COUNT);
+        let mut section_block_light = vec![0i8; SECTION_BLOCK_COUNT / 2];
+        let mut section_sky_light = vec![0i8; SECTION_BLOCK_COUNT / 2];
+
+        for local_y in 0..16 {
+            let y = section_y * 16 + local_y;
+
+            for z in 0..16 {
+                for x in 0..16 {
+                    let i = legacy_index(x, y, z);
+                    let index = (local_y * 16 + z) * 16 + x;
+
+                    for (array, section_array) in [
+                        (block_light, &mut section_block_light),
+                        (sky_light, &mut section_sky_light),
+                    ] {
+                        if let Some(array) = array {
+                            let value = get_legacy_nibble(array, i) as i8;
+                            section_array[index / 2] |=
+                                if index & 1 == 0 { value } else { value << 4 };
+                        }
+                    }
+
+                    let id = blocks[i] as u8;
+                    if id == 0 {
+                        continue;
+                    }
+
+                    let key = (id, get_legacy_nibble(data, i));
+                    let mut state = states
+                        .entry(key)
+                        .or_insert_with(|| legacy_block_state(key.0, key.1))
+                        .clone();
+
+                    // Like the game, grass and mycelium covered by snow are snowy
+                    if id == 2 || id == 110 {
+                        let above = blocks.get(i + 1).map(|v| *v as u8);
+                        let snowy = y + 1 < LEGACY_HEIGHT && matches!(above, Some(78 | 80));
+                        state
+                            .properties
+                            .insert("snowy".to_string(), snowy.to_string());
+                    }
+
+                    // Heights are stored as 1 + the Y of the highest block, from the bottom of the world
+                    let column = z * 16 + x;
+                    let height = (y as i32 - MIN_Y + 1) as u16;
+                    if (state.blocks_motion() || state.has_fluid())
+                        && motion_blocking[column] < height
+                    {
+                        motion_blocking[column] = height;
+                    }
+                    if state.blocks_motion() && ocean_floor[column] < height {
+                        ocean_floor[column] = height;
+                    }
+
+                    container.set(index, block_state_to_nbt(&state));
+                }
+            }
+        }
+
+        let mut section = Compound::new();
+        section.insert("Y".to_string(), Value::Byte(section_y as i8));
+        section.insert(
+            "block_states".to_string(),
+            container.to_nbt(calculate_bits_per_entry),
+        );
+        section.insert("biomes".to_string(), biomes.clone());
+
+        if block_light.is_some() {
+            section.insert(
+                "BlockLight".to_string(),
+                Value::ByteArray(ByteArray::new(section_block_light)),
+            );
+        }
+        if sky_light.is_some() {
+            section.insert(
+                "SkyLight".to_string(),
+                Value::ByteArray(ByteArray::new(section_sky_light)),
+            );
+        }
+
+        sections.push(Value::Compound(section));
+    }
+
+    let mut heightmaps = Compound::new();
+    heightmaps.insert(
+        "MOTION_BLOCKING".to_string(),
+        Value::LongArray(LongArray::new(encode_heightmap(&motion_blocking))),
+    );
+    heightmaps.insert(
+        "OCEAN_FLOOR".to_string(),
+        Value::LongArray(LongArray::new(encode_heightmap(&ocean_floor))),
+    );
+
+    let mut chunk = Compound::new();
+    chunk.insert("DataVersion".to_string(), Value::Int(0));
+    chunk.insert("xPos".to_string(), Value::Int(x_pos));
+    chunk.insert("zPos".to_string(), Value::Int(z_pos));
+    chunk.insert("yPos".to_string(), Value::Int(MIN_Y.div_euclid(16)));
+    chunk.insert(
+        "Status".to_string(),
+        Value::String("minecraft:full".to_string()),
+    );
+    chunk.insert("LastUpdate".to_string(), Value::Long(last_update));
+    chunk.insert("InhabitedTime".to_string(), Value::Long(0));
+    chunk.insert("sections".to_string(), Value::List(sections));
+    chunk.insert("Heightmaps".to_string(), Value::Compound(heightmaps));
+
+    Ok(Value::Compound(chunk))
+}
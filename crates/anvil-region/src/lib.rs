@@ -1,7 +1,22 @@
+pub mod archives;
+pub mod block_entities;
 pub mod blocks;
+pub mod borders;
 pub mod chunks;
+pub mod diff;
 pub mod dimensions;
+pub mod editing;
 pub mod heightmaps;
+pub mod legacy;
+pub mod light;
+pub mod linear;
 pub mod nbt;
 pub mod regions;
+pub mod schematics;
+pub mod search;
 pub mod sections;
+pub mod stats;
+pub mod structures;
+pub mod transforms;
+pub mod trim;
+pub mod worlds;
@@ -0,0 +1,104 @@
+use crate::nbt::Compound;
+use fastnbt::Value;
+use std::collections::HashMap;
+
+/// Light levels of a block, from 0 to 15
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightLevels {
+    pub block: u8,
+    pub sky: u8,
+}
+
+/// Read a value from a nibble array stored in YZX order.
+/// Returns None if the section has no such array
+fn get_nibble_at_position(
+    section: &Compound,
+    key: &str,
+    x: usize,
+    y: usize,
+    z: usize,
+) -> Result<Option<u8>, String> {
+    let array = match section.get(key) {
+        Some(Value::ByteArray(v)) => v,
+        None => return Ok(None),
+        v => return Err(format!("'{key}' is not a ByteArray. Got {v:?}")),
+    };
+
+    let index = (y * 16 + z) * 16 + x;
+    // Two values per byte, the lowest 4 bits being the even index
+    let byte = match array.get(index / 2) {
+        Some(v) => *v as u8,
+        None => {
+            return Err(format!(
+                "'{key}' index {index} out of bounds (length: {})",
+                array.len()
+            ))
+        }
+    };
+
+    let value = if index & 1 == 0 {
+        byte & 0x0F
+    } else {
+        byte >> 4
+    };
+
+    Ok(Some(value))
+}
+
+/// Block light at a position of the section. None if the section has no block light data
+pub fn get_block_light_at_position(
+    section: &Compound,
+    x: usize,
+    y: usize,
+    z: usize,
+) -> Result<Option<u8>, String> {
+    get_nibble_at_position(section, "BlockLight", x, y, z)
+}
+
+/// Sky light at a position of the section. None if the section has no sky light data
+pub fn get_sky_light_at_position(
+    section: &Compound,
+    x: usize,
+    y: usize,
+    z: usize,
+) -> Result<Option<u8>, String> {
+    get_nibble_at_position(section, "SkyLight", x, y, z)
+}
+
+/// Light levels at a position of the chunk, `y` being the world height.
+/// Like the game, a section without sky light takes the bottom level of the
+/// first section above it that has some, or the full sky light if there is none
+pub fn get_chunk_light_at_position(
+    sections: &HashMap<i32, &Compound>,
+    x: usize,
+    y: i32,
+    z: usize,
+) -> Result<LightLevels, String> {
+    let section_y = y.div_euclid(16);
+    let local_y = y.rem_euclid(16) as usize;
+
+    let block = match sections.get(&section_y) {
+        Some(section) => get_block_light_at_position(section, x, local_y, z)?.unwrap_or(0),
+        None => 0,
+    };
+
+    if let Some(section) = sections.get(&section_y) {
+        if let Some(sky) = get_sky_light_at_position(section, x, local_y, z)? {
+            return Ok(LightLevels { block, sky });
+        }
+    }
+
+    let max_section_y = sections.keys().copied().max().unwrap_or(section_y);
+    let mut sky = 15;
+
+    for above_y in (section_y + 1)..=max_section_y {
+        if let Some(section) = sections.get(&above_y) {
+            if let Some(v) = get_sky_light_at_position(section, x, 0, z)? {
+                sky = v;
+                break;
+            }
+        }
+    }
+
+    Ok(LightLevels { block, sky })
+}
@@ -0,0 +1,190 @@
+use crate::regions::{RawChunk, RegionFile, CHUNKS_PER_REGION, COMPRESSION_NONE};
+use ruzstd::{
+    decoding::StreamingDecoder,
+    encoding::{compress_to_vec, CompressionLevel},
+};
+use std::io::{self, Read};
+
+/// First and last 8 bytes of a `.linear` file
+pub const LINEAR_SIGNATURE: u64 = 0xc3ff_1318_3cca_9d9a;
+/// Versions 1 and 2 share the same layout
+pub const LINEAR_VERSION: u8 = 2;
+/// Signature, version, newest timestamp, compression level, chunk count,
+/// compressed length and 8 reserved bytes
+pub const LINEAR_HEADER_SIZE: usize = 32;
+/// ruzstd only implements the fastest level, which is close to zstd level 1
+const LINEAR_COMPRESSION_LEVEL: u8 = 1;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Whether the data starts with the signature of the linear format
+pub fn is_linear(data: &[u8]) -> bool {
+    data.len() >= 8 && u64::from_be_bytes(data[0..8].try_into().unwrap()) == LINEAR_SIGNATURE
+}
+
+impl RegionFile {
+    /// Read a `.linear` region file. The region is a single zstd frame holding a size and a
+    /// timestamp for each chunk, followed by the uncompressed NBT of the chunks
+    pub fn from_linear_bytes(data: &[u8]) -> io::Result<RegionFile> {
+        if data.len() < LINEAR_HEADER_SIZE + 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Linear region file too small",
+            ));
+        }
+
+        if !is_linear(data) || !is_linear(&data[data.len() - 8..]) {
+            return Err(invalid_data("Invalid linear region signature".to_string()));
+        }
+
+        let version = data[8];
+        if version != 1 && version != 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Unsupported linear region version {version}"),
+            ));
+        }
+
+        let length = u32::from_be_bytes(data[20..24].try_into().unwrap()) as usize;
+        let compressed = data
+            .get(LINEAR_HEADER_SIZE..LINEAR_HEADER_SIZE + length)
+            .ok_or_else(|| invalid_data(format!("Linear region truncated ({length} bytes)")))?;
+
+        let mut decoder = StreamingDecoder::new(compressed)
+            .map_err(|e| invalid_data(format!("Invalid zstd frame: {e}")))?;
+        let mut decompressed = Vec::new();
+        decoder.read_to_end(&mut decompressed)?;
+
+        let header_size = CHUNKS_PER_REGION * 8;
+        if decompressed.len() < header_size {
+            return Err(invalid_data("Linear region header truncated".to_string()));
+        }
+
+        let mut region = RegionFile::default();
+        let mut offset = header_size;
+
+        for i in 0..CHUNKS_PER_REGION {
+            let idx = i * 8;
+            let size = u32::from_be_bytes(decompressed[idx..idx + 4].try_into().unwrap()) as usize;
+            let timestamp = u32::from_be_bytes(decompressed[idx + 4..idx + 8].try_into().unwrap());
+
+            if size == 0 {
+                continue;
+            }
+
+            let chunk_data = decompressed
+                .get(offset..offset + size)
+                .ok_or_else(|| invalid_data(format!("Chunk {i} truncated ({size} bytes)")))?;
+            offset += size;
+
+            region.chunks[i] = Some(RawChunk {
+                timestamp,
+                compression: COMPRESSION_NONE,
+                data: chunk_data.to_vec(),
+            });
+        }
+
+        Ok(region)
+    }
+
+    /// Encode the region file in the linear format. The chunks are decompressed first
+    pub fn to_linear_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut header = Vec::with_capacity(CHUNKS_PER_REGION * 8);
+        let mut chunks = Vec::new();
+        let mut chunk_count: u16 = 0;
+        let mut newest_timestamp: u32 = 0;
+
+        for raw in &self.chunks {
+            let Some(raw) = raw else {
+                header.extend_from_slice(&[0; 8]);
+                continue;
+            };
+
+            let nbt = raw.decompress()?;
+            header.extend_from_slice(&(nbt.len() as u32).to_be_bytes());
+            header.extend_from_slice(&raw.timestamp.to_be_bytes());
+            chunks.extend_from_slice(&nbt);
+
+            chunk_count += 1;
+            newest_timestamp = newest_timestamp.max(raw.timestamp);
+        }
+
+        header.extend_from_slice(&chunks);
+        let compressed = compress_to_vec(header.as_slice(), CompressionLevel::Fastest);
+
+        let mut bytes = Vec::with_capacity(LINEAR_HEADER_SIZE + compressed.len() + 8);
+        bytes.extend_from_slice(&LINEAR_SIGNATURE.to_be_bytes());
+        bytes.push(LINEAR_VERSION);
+        bytes.extend_from_slice(&(newest_timestamp as i64).to_be_bytes());
+        bytes.push(LINEAR_COMPRESSION_LEVEL);
+        bytes.extend_from_slice(&chunk_count.to_be_bytes());
+        bytes.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+        bytes.extend_from_slice(&compressed);
+        bytes.extend_from_slice(&LINEAR_SIGNATURE.to_be_bytes());
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::Compound;
+    use fastnbt::{LongArray, Value};
+
+    fn chunk_nbt(x: i32, z: i32) -> Value {
+        let mut root = Compound::new();
+        root.insert("DataVersion".to_string(), Value::Int(3953));
+        root.insert("xPos".to_string(), Value::Int(x));
+        root.insert("zPos".to_string(), Value::Int(z));
+        root.insert(
+            "Status".to_string(),
+            Value::String("minecraft:full".to_string()),
+        );
+        root.insert(
+            "data".to_string(),
+            Value::LongArray(LongArray::new(
+                (0..256).map(|i| i * (x + z) as i64).collect(),
+            )),
+        );
+        Value::Compound(root)
+    }
+
+    #[test]
+    fn anvil_linear_anvil_round_trip() {
+        let mut region = RegionFile::default();
+        for (x, z) in [(0, 0), (31, 0), (5, 7), (0, 31), (31, 31)] {
+            let index = x as usize + z as usize * 32;
+            region.chunks[index] =
+                Some(RawChunk::from_nbt(&chunk_nbt(x, z), 1_700_000_000 + index as u32).unwrap());
+        }
+
+        let anvil = RegionFile::from_bytes(&region.to_bytes().unwrap()).unwrap();
+        let linear_bytes = anvil.to_linear_bytes().unwrap();
+        assert!(is_linear(&linear_bytes));
+
+        let linear = RegionFile::from_bytes(&linear_bytes).unwrap();
+        let mut back = linear.clone();
+        back.compress_chunks().unwrap();
+        let back = RegionFile::from_bytes(&back.to_bytes().unwrap()).unwrap();
+
+        for (i, original) in region.chunks.iter().enumerate() {
+            for converted in [&anvil, &linear, &back] {
+                match (original, &converted.chunks[i]) {
+                    (Some(original), Some(converted)) => {
+                        assert_eq!(
+                            converted.decompress().unwrap(),
+                            original.decompress().unwrap()
+                        );
+                        assert_eq!(converted.timestamp, original.timestamp);
+                    }
+                    (None, None) => {}
+                    _ => panic!("Chunk {i} is only in one of the regions"),
+                }
+            }
+        }
+    }
+}
@@ -1,82 +1,333 @@
-use crate::chunks::{Chunk, ChunkPosition};
+use crate::{
+    chunks::{Chunk, ChunkPosition},
+    legacy::{convert_legacy_chunk, is_legacy_chunk},
+    linear::is_linear,
+};
 use fastnbt::from_bytes;
 use fastnbt::Value;
-use flate2::read::ZlibDecoder;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
 use std::io;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::path::Path;
 
 pub const CHUNKS_PER_REGION: usize = 1024;
+pub const SECTOR_SIZE: usize = 4096;
+/// Locations then timestamps, 4 bytes per chunk each
+pub const HEADER_SIZE: usize = 2 * SECTOR_SIZE;
+
+pub const COMPRESSION_GZIP: u8 = 1;
+pub const COMPRESSION_ZLIB: u8 = 2;
+pub const COMPRESSION_NONE: u8 = 3;
+
+/// How the chunks of a region file are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionFormat {
+    /// `.mca` files, with each chunk compressed on its own and aligned on 4KiB sectors
+    Anvil,
+    /// `.linear` files used by some Paper forks, with the whole region in a zstd frame
+    Linear,
+    /// `.mcr` files of the worlds saved before 1.2, with the same layout as anvil files
+    /// but chunks of 128 blocks high using numeric block ids
+    McRegion,
+}
+
+impl RegionFormat {
+    pub fn from_path(path: &Path) -> Option<RegionFormat> {
+        match path.extension()?.to_str()? {
+            "mca" => Some(RegionFormat::Anvil),
+            "linear" => Some(RegionFormat::Linear),
+            "mcr" => Some(RegionFormat::McRegion),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RegionFormat::Anvil => "mca",
+            RegionFormat::Linear => "linear",
+            RegionFormat::McRegion => "mcr",
+        }
+    }
+
+    pub fn encode(&self, region_file: &RegionFile) -> io::Result<Vec<u8>> {
+        match self {
+            RegionFormat::Anvil | RegionFormat::McRegion => region_file.to_bytes(),
+            RegionFormat::Linear => region_file.to_linear_bytes(),
+        }
+    }
+}
 
 /// A region is a 32x32 chunks area
 pub struct Region {
     pub chunks: Vec<Chunk>,
 }
 
-pub fn parse_region_bytes(data: &[u8]) -> std::io::Result<Region> {
-    if data.len() < 8192 {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::UnexpectedEof,
-            "Region file too small",
-        ));
+/// A chunk as stored in a region file, before decompression
+#[derive(Debug, Clone)]
+pub struct RawChunk {
+    /// Last time the chunk was saved, in seconds since the epoch
+    pub timestamp: u32,
+    pub compression: u8,
+    pub data: Vec<u8>,
+}
+
+impl RawChunk {
+    /// Compress the NBT of a chunk with zlib, like the game does by default
+    pub fn from_nbt(nbt: &Value, timestamp: u32) -> io::Result<RawChunk> {
+        let bytes =
+            fastnbt::to_bytes(nbt).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        RawChunk::from_nbt_bytes(&bytes, timestamp)
+    }
+
+    /// Compress already encoded NBT with zlib
+    pub fn from_nbt_bytes(bytes: &[u8], timestamp: u32) -> io::Result<RawChunk> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes)?;
+
+        Ok(RawChunk {
+            timestamp,
+            compression: COMPRESSION_ZLIB,
+            data: encoder.finish()?,
+        })
     }
 
-    let mut chunks = Vec::with_capacity(CHUNKS_PER_REGION);
-    let header = &data[..8192];
+    pub fn is_supported(&self) -> bool {
+        matches!(
+            self.compression,
+            COMPRESSION_GZIP | COMPRESSION_ZLIB | COMPRESSION_NONE
+        )
+    }
+
+    /// Decompress the NBT bytes of the chunk
+    pub fn decompress(&self) -> io::Result<Vec<u8>> {
+        let mut decompressed = Vec::new();
+
+        match self.compression {
+            COMPRESSION_GZIP => {
+                GzDecoder::new(self.data.as_slice()).read_to_end(&mut decompressed)?;
+            }
+            COMPRESSION_ZLIB => {
+                ZlibDecoder::new(self.data.as_slice()).read_to_end(&mut decompressed)?;
+            }
+            COMPRESSION_NONE => decompressed.extend_from_slice(&self.data),
+            v => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("Unsupported chunk compression type {v}"),
+                ))
+            }
+        }
+
+        Ok(decompressed)
+    }
+}
+
+/// The chunks of a region file, kept compressed until they are needed.
+/// Unlike `Region`, it also keeps the chunks that are not fully generated, so it can be written back
+#[derive(Debug, Clone)]
+pub struct RegionFile {
+    /// Indexed by `local_x + local_z * 32`
+    pub chunks: Vec<Option<RawChunk>>,
+}
 
-    for i in 0..CHUNKS_PER_REGION {
-        let idx = i * 4;
-        let offset = ((header[idx] as u32) << 16)
-            | ((header[idx + 1] as u32) << 8)
-            | (header[idx + 2] as u32);
-        let sector_count = header[idx + 3];
+impl Default for RegionFile {
+    fn default() -> Self {
+        RegionFile {
+            chunks: vec![None; CHUNKS_PER_REGION],
+        }
+    }
+}
 
-        if offset == 0 || sector_count == 0 {
-            continue;
+impl RegionFile {
+    /// Read an anvil or a linear region file, depending on its signature
+    pub fn from_bytes(data: &[u8]) -> io::Result<RegionFile> {
+        if is_linear(data) {
+            RegionFile::from_linear_bytes(data)
+        } else {
+            RegionFile::from_anvil_bytes(data)
         }
+    }
+
+    pub fn from_anvil_bytes(data: &[u8]) -> io::Result<RegionFile> {
+        if data.len() < HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Region file too small",
+            ));
+        }
+
+        let mut region = RegionFile::default();
+
+        for i in 0..CHUNKS_PER_REGION {
+            let idx = i * 4;
+            let offset =
+                ((data[idx] as u32) << 16) | ((data[idx + 1] as u32) << 8) | (data[idx + 2] as u32);
+            let sector_count = data[idx + 3];
+
+            if offset == 0 || sector_count == 0 {
+                continue;
+            }
 
-        let byte_offset = offset as usize * 4096;
-        if byte_offset + 5 > data.len() {
-            continue;
+            let byte_offset = offset as usize * SECTOR_SIZE;
+            if byte_offset + 5 > data.len() {
+                continue;
+            }
+
+            let length = u32::from_be_bytes(data[byte_offset..byte_offset + 4].try_into().unwrap());
+            let compression = data[byte_offset + 4];
+
+            let data_start = byte_offset + 5;
+            let data_end = data_start + (length as usize).saturating_sub(1);
+            if length == 0 || data_end > data.len() {
+                continue;
+            }
+
+            let timestamp = u32::from_be_bytes(
+                data[SECTOR_SIZE + idx..SECTOR_SIZE + idx + 4]
+                    .try_into()
+                    .unwrap(),
+            );
+
+            region.chunks[i] = Some(RawChunk {
+                timestamp,
+                compression,
+                data: data[data_start..data_end].to_vec(),
+            });
         }
 
-        let length = u32::from_be_bytes(data[byte_offset..byte_offset + 4].try_into().unwrap());
-        let compression_type = data[byte_offset + 4];
+        Ok(region)
+    }
+
+    /// Encode the region file, each chunk being aligned on 4KiB sectors
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![0u8; HEADER_SIZE];
+        let mut sector = (HEADER_SIZE / SECTOR_SIZE) as u32;
+
+        for (i, raw) in self.chunks.iter().enumerate() {
+            let Some(raw) = raw else {
+                continue;
+            };
+
+            let length = raw.data.len() + 1;
+            let sector_count = (length + 4).div_ceil(SECTOR_SIZE);
+
+            // Bigger chunks are stored in external .mcc files by the game
+            if sector_count > 255 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Chunk {i} is too big ({length} bytes)"),
+                ));
+            }
+
+            let idx = i * 4;
+            bytes[idx] = (sector >> 16) as u8;
+            bytes[idx + 1] = (sector >> 8) as u8;
+            bytes[idx + 2] = sector as u8;
+            bytes[idx + 3] = sector_count as u8;
+            bytes[SECTOR_SIZE + idx..SECTOR_SIZE + idx + 4]
+                .copy_from_slice(&raw.timestamp.to_be_bytes());
+
+            bytes.extend_from_slice(&(length as u32).to_be_bytes());
+            bytes.push(raw.compression);
+            bytes.extend_from_slice(&raw.data);
+            bytes.resize(bytes.len().div_ceil(SECTOR_SIZE) * SECTOR_SIZE, 0);
 
-        let data_start = byte_offset + 5;
-        let data_end = data_start + (length - 1) as usize;
-        if data_end > data.len() {
-            continue;
+            sector += sector_count as u32;
         }
 
-        let compressed_data = &data[data_start..data_end];
-        let chunk_data = match compression_type {
-            2 => {
-                let mut decoder = ZlibDecoder::new(compressed_data);
-                let mut decompressed = Vec::new();
-                decoder.read_to_end(&mut decompressed)?;
-                decompressed
+        Ok(bytes)
+    }
+
+    /// Compress the uncompressed chunks with zlib, e.g. the chunks read from a linear region,
+    /// since older versions of the game cannot read uncompressed chunks
+    pub fn compress_chunks(&mut self) -> io::Result<()> {
+        for raw in self.chunks.iter_mut().flatten() {
+            if raw.compression == COMPRESSION_NONE {
+                *raw = RawChunk::from_nbt_bytes(&raw.data, raw.timestamp)?;
             }
-            _ => continue,
-        };
+        }
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.iter().all(|c| c.is_none())
+    }
+
+    pub fn get_raw_chunk(&self, local_x: i32, local_z: i32) -> Option<&RawChunk> {
+        self.chunks[get_chunk_index(local_x, local_z)].as_ref()
+    }
+
+    /// Decompress and parse a chunk. Returns None if there is no fully generated chunk there
+    pub fn get_chunk(&self, local_x: i32, local_z: i32) -> io::Result<Option<Chunk>> {
+        let i = get_chunk_index(local_x, local_z);
+
+        match &self.chunks[i] {
+            Some(raw) if raw.is_supported() => parse_raw_chunk(i, raw),
+            _ => Ok(None),
+        }
+    }
 
-        let chunk = match parse_chunk_from_bytes(i as i32, chunk_data) {
-            Ok(Some(v)) => v,
-            Ok(None) => continue,
-            Err(e) => return Err(io::Error::new(io::ErrorKind::Unsupported, e)),
-        };
+    /// Replace the chunk at its position in the region
+    pub fn set_chunk(&mut self, chunk: &Chunk, timestamp: u32) -> io::Result<()> {
+        let i = get_chunk_index(chunk.position.x, chunk.position.z);
+        self.chunks[i] = Some(RawChunk::from_nbt(&chunk.nbt, timestamp)?);
+        Ok(())
+    }
+
+    pub fn remove_chunk(&mut self, local_x: i32, local_z: i32) -> Option<RawChunk> {
+        self.chunks[get_chunk_index(local_x, local_z)].take()
+    }
+
+    /// Parse all the fully generated chunks
+    pub fn to_region(&self) -> io::Result<Region> {
+        let mut chunks = Vec::with_capacity(CHUNKS_PER_REGION);
+
+        for (i, raw) in self.chunks.iter().enumerate() {
+            match raw {
+                Some(raw) if raw.is_supported() => {
+                    if let Some(chunk) = parse_raw_chunk(i, raw)? {
+                        chunks.push(chunk);
+                    }
+                }
+                _ => continue,
+            }
+        }
 
-        chunks.push(chunk);
+        Ok(Region { chunks })
     }
+}
+
+/// Index of a chunk in the region header. Also accepts absolute chunk coordinates
+pub fn get_chunk_index(x: i32, z: i32) -> usize {
+    (x.rem_euclid(32) + z.rem_euclid(32) * 32) as usize
+}
 
-    Ok(Region { chunks })
+fn parse_raw_chunk(i: usize, raw: &RawChunk) -> io::Result<Option<Chunk>> {
+    parse_chunk_from_bytes(i as i32, raw.decompress()?)
+        .map_err(|e| io::Error::new(io::ErrorKind::Unsupported, e))
+}
+
+pub fn parse_region_bytes(data: &[u8]) -> std::io::Result<Region> {
+    RegionFile::from_bytes(data)?.to_region()
 }
 
 pub fn parse_chunk_from_bytes(i: i32, bytes: Vec<u8>) -> Result<Option<Chunk>, String> {
-    let nbt: Value = match from_bytes(&bytes) {
+    let mut nbt: Value = match from_bytes(&bytes) {
         Ok(v) => v,
         Err(_) => return Ok(None),
     };
 
+    // McRegion chunks are converted to the modern layout
+    if let Value::Compound(root) = &nbt {
+        if is_legacy_chunk(root) {
+            nbt = convert_legacy_chunk(root)?;
+        }
+    }
+
     let Value::Compound(ref root) = nbt else {
         return Ok(None);
     };
@@ -90,15 +341,19 @@ pub fn parse_chunk_from_bytes(i: i32, bytes: Vec<u8>) -> Result<Option<Chunk>, S
 
     let data_version = match root.get("DataVersion") {
         Some(Value::Int(v)) => *v,
-        v => return Err(format!("'DataVersion' not found or not an Int. Got {v:#?}").into()),
+        v => return Err(format!("'DataVersion' not found or not an Int. Got {v:#?}")),
     };
     let last_update = match root.get("LastUpdate") {
         Some(Value::Long(v)) => *v,
-        v => return Err(format!("'LastUpdate' not found or not a Long. Got {v:#?}").into()),
+        v => return Err(format!("'LastUpdate' not found or not a Long. Got {v:#?}")),
     };
     let inhabited_time = match root.get("InhabitedTime") {
         Some(Value::Long(v)) => *v,
-        v => return Err(format!("'InhabitedTime' not found or not a Long. Got {v:#?}").into()),
+        v => {
+            return Err(format!(
+                "'InhabitedTime' not found or not a Long. Got {v:#?}"
+            ))
+        }
     };
 
     let chunk_x = root
@@ -0,0 +1,1090 @@
+use crate::{
+    block_entities::{parse_block_entities, BlockEntity},
+    blocks::{BlockPosition, BlockState, BoundingBox},
+    chunks::parse_chunk_sections,
+    dimensions::Dimension,
+    editing::{block_state_to_nbt, edit_world_area},
+    nbt::Compound,
+    search::BlockQuery,
+    sections::{
+        decode_biome_indices, decode_block_indices, parse_biome_palette, parse_block_palette,
+        parse_block_state,
+    },
+    transforms::Transform,
+    worlds::World,
+};
+use fastnbt::{ByteArray, IntArray, LongArray, Value};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+    collections::HashMap,
+    error::Error,
+    io,
+    io::{Read, Write},
+};
+
+/// Blocks that are not pasted, used for the positions a structure file does not define
+pub const STRUCTURE_VOID: &str = "minecraft:structure_void";
+
+/// Biome of each block of a volume
+#[derive(Debug, Clone)]
+pub struct VolumeBiomes {
+    pub palette: Vec<String>,
+    /// Palette index of each block, in the same order as the blocks
+    pub indices: Vec<usize>,
+}
+
+/// The blocks, biomes and block entities of a box of the world
+#[derive(Debug, Clone)]
+pub struct BlockVolume {
+    pub size_x: usize,
+    pub size_y: usize,
+    pub size_z: usize,
+    pub data_version: i32,
+    /// The first entry is always air
+    pub palette: Vec<BlockState>,
+    /// Palette index of each block, stored in YZX order
+    pub blocks: Vec<usize>,
+    /// None if the format does not store biomes
+    pub biomes: Option<VolumeBiomes>,
+    /// Positions are relative to the volume
+    pub block_entities: Vec<BlockEntity>,
+}
+
+impl BlockVolume {
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (y * self.size_z + z) * self.size_x + x
+    }
+
+    pub fn volume(&self) -> usize {
+        self.size_x * self.size_y * self.size_z
+    }
+
+    /// Mirror and rotate the volume. It still starts at 0 0 0 after the transformation
+    pub fn transformed(&self, transform: &Transform) -> BlockVolume {
+        if transform.is_identity() {
+            return self.clone();
+        }
+
+        let (size_x, size_z) = if transform.swaps_axes() {
+            (self.size_z, self.size_x)
+        } else {
+            (self.size_x, self.size_z)
+        };
+
+        // Offset to bring the transformed corners back to positive coordinates
+        let (corner_x, corner_z) = transform.apply(self.size_x as i32 - 1, self.size_z as i32 - 1);
+        let (offset_x, offset_z) = (-corner_x.min(0), -corner_z.min(0));
+        let move_position = |x: i32, z: i32| {
+            let (x, z) = transform.apply(x, z);
+            (x + offset_x, z + offset_z)
+        };
+
+        let mut blocks = vec![0; self.blocks.len()];
+        let mut biome_indices = self.biomes.as_ref().map(|b| vec![0; b.indices.len()]);
+
+        for y in 0..self.size_y {
+            for z in 0..self.size_z {
+                for x in 0..self.size_x {
+                    let (new_x, new_z) = move_position(x as i32, z as i32);
+                    let from = self.index(x, y, z);
+                    let to = (y * size_z + new_z as usize) * size_x + new_x as usize;
+
+                    blocks[to] = self.blocks[from];
+                    if let (Some(indices), Some(biomes)) = (&mut biome_indices, &self.biomes) {
+                        indices[to] = biomes.indices[from];
+                    }
+                }
+            }
+        }
+
+        let block_entities = self
+            .block_entities
+            .iter()
+            .map(|be| {
+                let (x, z) = move_position(be.position.x, be.position.z);
+                BlockEntity {
+                    id: be.id.clone(),
+                    position: BlockPosition {
+                        x,
+                        y: be.position.y,
+                        z,
+                    },
+                    data: be.data.clone(),
+                }
+            })
+            .collect();
+
+        BlockVolume {
+            size_x,
+            size_y: self.size_y,
+            size_z,
+            data_version: self.data_version,
+            palette: self
+                .palette
+                .iter()
+                .map(|b| transform.apply_block_state(b))
+                .collect(),
+            blocks,
+            biomes: self
+                .biomes
+                .as_ref()
+                .zip(biome_indices)
+                .map(|(b, indices)| VolumeBiomes {
+                    palette: b.palette.clone(),
+                    indices,
+                }),
+            block_entities,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchematicFormat {
+    /// Sponge schematic v3 (`.schem`), used by WorldEdit
+    Sponge,
+    /// Vanilla structure block file (`.nbt`)
+    Structure,
+    /// Litematica schematic (`.litematic`)
+    Litematica,
+}
+
+impl SchematicFormat {
+    pub fn from_path(path: &str) -> Option<SchematicFormat> {
+        let path = path.to_lowercase();
+
+        if path.ends_with(".schem") {
+            Some(SchematicFormat::Sponge)
+        } else if path.ends_with(".nbt") {
+            Some(SchematicFormat::Structure)
+        } else if path.ends_with(".litematic") {
+            Some(SchematicFormat::Litematica)
+        } else {
+            None
+        }
+    }
+}
+
+/// Copy the blocks, biomes and block entities of an area of the world.
+/// Missing chunks and sections are filled with air. Fails if there is no chunk in the area,
+/// as the data version of the volume comes from its chunks
+pub fn extract_volume(world: &World, area: &BoundingBox) -> Result<BlockVolume, Box<dyn Error>> {
+    let size_x = area.size_x() as usize;
+    let size_y = area.size_y() as usize;
+    let size_z = area.size_z() as usize;
+    let volume_size = size_x * size_y * size_z;
+
+    let mut palette = vec![BlockState::air()];
+    let mut palette_map: HashMap<BlockState, usize> = HashMap::from([(BlockState::air(), 0)]);
+    let mut blocks = vec![0; volume_size];
+
+    let mut biome_palette = vec!["minecraft:plains".to_string()];
+    let mut biome_map: HashMap<String, usize> = HashMap::from([(biome_palette[0].clone(), 0)]);
+    let mut biomes = vec![0; volume_size];
+
+    let mut block_entities = Vec::new();
+    let mut data_version = 0;
+    let mut found_chunk = false;
+
+    let min_chunk_x = area.min_x.div_euclid(16);
+    let max_chunk_x = area.max_x.div_euclid(16);
+    let min_chunk_z = area.min_z.div_euclid(16);
+    let max_chunk_z = area.max_z.div_euclid(16);
+
+    for region_x in min_chunk_x.div_euclid(32)..=max_chunk_x.div_euclid(32) {
+        for region_z in min_chunk_z.div_euclid(32)..=max_chunk_z.div_euclid(32) {
+            let Some(info) = world.region(region_x, region_z) else {
+                continue;
+            };
+            let region_file = world.read_region_file_raw(info)?;
+
+            for chunk_x in min_chunk_x.max(region_x * 32)..=max_chunk_x.min(region_x * 32 + 31) {
+                for chunk_z in min_chunk_z.max(region_z * 32)..=max_chunk_z.min(region_z * 32 + 31)
+                {
+                    let Some(chunk) = region_file.get_chunk(chunk_x, chunk_z)? else {
+                        continue;
+                    };
+                    let Value::Compound(root) = &chunk.nbt else {
+                        return Err("Root NBT is not a compound".into());
+                    };
+
+                    data_version = data_version.max(chunk.data_version);
+                    found_chunk = true;
+
+                    // Part of the area inside this chunk, in world coordinates
+                    let x_range = area.min_x.max(chunk_x * 16)..=area.max_x.min(chunk_x * 16 + 15);
+                    let z_range = area.min_z.max(chunk_z * 16)..=area.max_z.min(chunk_z * 16 + 15);
+
+                    let sections = parse_chunk_sections(root)?;
+
+                    for section_y in area.min_y.div_euclid(16)..=area.max_y.div_euclid(16) {
+                        let Some(section) = sections.get(&section_y) else {
+                            continue;
+                        };
+                        if !section.contains_key("block_states") {
+                            continue;
+                        }
+
+                        let section_palette = parse_block_palette(section)?;
+                        let indices = decode_block_indices(section, section_palette.len())?;
+                        let global_indices: Vec<usize> = section_palette
+                            .into_iter()
+                            .map(|block| {
+                                *palette_map.entry(block.clone()).or_insert_with(|| {
+                                    palette.push(block);
+                                    palette.len() - 1
+                                })
+                            })
+                            .collect();
+
+                        let (section_biome_palette, biome_indices) =
+                            if section.contains_key("biomes") {
+                                let p = parse_biome_palette(section)?;
+                                let i = decode_biome_indices(section, p.len())?;
+                                (p, i)
+                            } else {
+                                (vec![biome_palette[0].clone()], vec![0; 64])
+                            };
+                        let global_biome_indices: Vec<usize> = section_biome_palette
+                            .into_iter()
+                            .map(|biome| {
+                                *biome_map.entry(biome.clone()).or_insert_with(|| {
+                                    biome_palette.push(biome);
+                                    biome_palette.len() - 1
+                                })
+                            })
+                            .collect();
+
+                        let y_range =
+                            area.min_y.max(section_y * 16)..=area.max_y.min(section_y * 16 + 15);
+
+                        for y in y_range {
+                            let local_y = y.rem_euclid(16) as usize;
+                            for z in z_range.clone() {
+                                let local_z = z.rem_euclid(16) as usize;
+                                for x in x_range.clone() {
+                                    let local_x = x.rem_euclid(16) as usize;
+
+                                    let volume_index = ((y - area.min_y) as usize * size_z
+                                        + (z - area.min_z) as usize)
+                                        * size_x
+                                        + (x - area.min_x) as usize;
+
+                                    let block_index = (local_y * 16 + local_z) * 16 + local_x;
+                                    blocks[volume_index] = global_indices[indices[block_index]];
+
+                                    let biome_index =
+                                        ((local_y / 4) * 4 + local_z / 4) * 4 + local_x / 4;
+                                    biomes[volume_index] =
+                                        global_biome_indices[biome_indices[biome_index]];
+                                }
+                            }
+                        }
+                    }
+
+                    for mut block_entity in parse_block_entities(root)? {
+                        let p = &block_entity.position;
+                        if !area.contains(p.x, p.y, p.z) {
+                            continue;
+                        }
+
+                        block_entity.position = BlockPosition {
+                            x: p.x - area.min_x,
+                            y: p.y - area.min_y,
+                            z: p.z - area.min_z,
+                        };
+                        block_entities.push(block_entity);
+                    }
+                }
+            }
+        }
+    }
+
+    if !found_chunk {
+        return Err("No chunk found in the area".into());
+    }
+
+    Ok(BlockVolume {
+        size_x,
+        size_y,
+        size_z,
+        data_version,
+        palette,
+        blocks,
+        biomes: Some(VolumeBiomes {
+            palette: biome_palette,
+            indices: biomes,
+        }),
+        block_entities,
+    })
+}
+
+/// Encode a volume in the given format, gzip compressed.
+/// `timestamp` is only used by Litematica, in milliseconds since the epoch
+pub fn export_schematic(
+    volume: &BlockVolume,
+    format: SchematicFormat,
+    name: &str,
+    timestamp: i64,
+) -> io::Result<Vec<u8>> {
+    let nbt = match format {
+        SchematicFormat::Sponge => to_sponge_schematic(volume, name),
+        SchematicFormat::Structure => to_structure_nbt(volume),
+        SchematicFormat::Litematica => to_litematic(volume, name, timestamp),
+    };
+
+    let bytes =
+        fastnbt::to_bytes(&nbt).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&bytes)?;
+    encoder.finish()
+}
+
+fn compound<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::Compound(
+        entries
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect(),
+    )
+}
+
+/// Unsigned LEB128, used by the Sponge format for palette indices
+fn write_varint(bytes: &mut Vec<i8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            bytes.push(byte as i8);
+            break;
+        }
+        bytes.push((byte | 0x80) as i8);
+    }
+}
+
+/// Sponge schematic v3
+pub fn to_sponge_schematic(volume: &BlockVolume, name: &str) -> Value {
+    let block_palette: Compound = volume
+        .palette
+        .iter()
+        .enumerate()
+        .map(|(i, block)| (block.to_string(), Value::Int(i as i32)))
+        .collect();
+
+    let mut block_data = Vec::with_capacity(volume.blocks.len());
+    for &index in &volume.blocks {
+        write_varint(&mut block_data, index);
+    }
+
+    let block_entities = volume
+        .block_entities
+        .iter()
+        .map(|be| {
+            compound([
+                (
+                    "Pos",
+                    Value::IntArray(IntArray::new(vec![
+                        be.position.x,
+                        be.position.y,
+                        be.position.z,
+                    ])),
+                ),
+                ("Id", Value::String(be.id.clone())),
+                ("Data", Value::Compound(be.data.clone())),
+            ])
+        })
+        .collect();
+
+    let mut schematic = match compound([
+        ("Version", Value::Int(3)),
+        ("DataVersion", Value::Int(volume.data_version)),
+        (
+            "Metadata",
+            compound([("Name", Value::String(name.to_string()))]),
+        ),
+        // Sizes are unsigned shorts
+        ("Width", Value::Short(volume.size_x as u16 as i16)),
+        ("Height", Value::Short(volume.size_y as u16 as i16)),
+        ("Length", Value::Short(volume.size_z as u16 as i16)),
+        ("Offset", Value::IntArray(IntArray::new(vec![0, 0, 0]))),
+        (
+            "Blocks",
+            compound([
+                ("Palette", Value::Compound(block_palette)),
+                ("Data", Value::ByteArray(ByteArray::new(block_data))),
+                ("BlockEntities", Value::List(block_entities)),
+            ]),
+        ),
+    ]) {
+        Value::Compound(v) => v,
+        _ => unreachable!(),
+    };
+
+    if let Some(biomes) = &volume.biomes {
+        let biome_palette: Compound = biomes
+            .palette
+            .iter()
+            .enumerate()
+            .map(|(i, biome)| (biome.clone(), Value::Int(i as i32)))
+            .collect();
+
+        let mut biome_data = Vec::with_capacity(biomes.indices.len());
+        for &index in &biomes.indices {
+            write_varint(&mut biome_data, index);
+        }
+
+        schematic.insert(
+            "Biomes".to_string(),
+            compound([
+                ("Palette", Value::Compound(biome_palette)),
+                ("Data", Value::ByteArray(ByteArray::new(biome_data))),
+            ]),
+        );
+    }
+
+    compound([("Schematic", Value::Compound(schematic))])
+}
+
+/// Vanilla structure file. This format cannot store biomes
+pub fn to_structure_nbt(volume: &BlockVolume) -> Value {
+    let block_entities: HashMap<(i32, i32, i32), &BlockEntity> = volume
+        .block_entities
+        .iter()
+        .map(|be| ((be.position.x, be.position.y, be.position.z), be))
+        .collect();
+
+    let mut blocks = Vec::with_capacity(volume.blocks.len());
+
+    for y in 0..volume.size_y {
+        for z in 0..volume.size_z {
+            for x in 0..volume.size_x {
+                let state = volume.blocks[volume.index(x, y, z)];
+                let pos = (x as i32, y as i32, z as i32);
+
+                let mut block = match compound([
+                    ("state", Value::Int(state as i32)),
+                    (
+                        "pos",
+                        Value::List(vec![
+                            Value::Int(pos.0),
+                            Value::Int(pos.1),
+                            Value::Int(pos.2),
+                        ]),
+                    ),
+                ]) {
+                    Value::Compound(v) => v,
+                    _ => unreachable!(),
+                };
+
+                if let Some(be) = block_entities.get(&pos) {
+                    let mut data = be.data.clone();
+                    data.insert("id".to_string(), Value::String(be.id.clone()));
+                    block.insert("nbt".to_string(), Value::Compound(data));
+                }
+
+                blocks.push(Value::Compound(block));
+            }
+        }
+    }
+
+    compound([
+        ("DataVersion", Value::Int(volume.data_version)),
+        (
+            "size",
+            Value::List(vec![
+                Value::Int(volume.size_x as i32),
+                Value::Int(volume.size_y as i32),
+                Value::Int(volume.size_z as i32),
+            ]),
+        ),
+        (
+            "palette",
+            Value::List(volume.palette.iter().map(block_state_to_nbt).collect()),
+        ),
+        ("blocks", Value::List(blocks)),
+        ("entities", Value::List(Vec::new())),
+    ])
+}
+
+/// Bits per entry of the Litematica block states, with a minimum of 2
+fn litematica_bits_per_entry(palette_size: usize) -> usize {
+    ((palette_size as f64).log2().ceil() as usize).max(2)
+}
+
+/// Unlike the chunk format, Litematica entries can span two longs
+fn pack_litematica_indices(indices: &[usize], bits_per_entry: usize) -> Vec<i64> {
+    let mut data = vec![0u64; (indices.len() * bits_per_entry).div_ceil(64)];
+
+    for (i, &value) in indices.iter().enumerate() {
+        let start_offset = i * bits_per_entry;
+        let start_long = start_offset / 64;
+        let end_long = ((i + 1) * bits_per_entry - 1) / 64;
+        let bit_offset = start_offset % 64;
+
+        data[start_long] |= (value as u64) << bit_offset;
+
+        if start_long != end_long {
+            data[end_long] |= (value as u64) >> (64 - bit_offset);
+        }
+    }
+
+    data.into_iter().map(|v| v as i64).collect()
+}
+
+/// Litematica schematic with a single region
+pub fn to_litematic(volume: &BlockVolume, name: &str, timestamp: i64) -> Value {
+    let size = || {
+        compound([
+            ("x", Value::Int(volume.size_x as i32)),
+            ("y", Value::Int(volume.size_y as i32)),
+            ("z", Value::Int(volume.size_z as i32)),
+        ])
+    };
+
+    let total_blocks = volume
+        .blocks
+        .iter()
+        .filter(|&&i| !volume.palette[i].is_air())
+        .count();
+
+    let block_states = pack_litematica_indices(
+        &volume.blocks,
+        litematica_bits_per_entry(volume.palette.len()),
+    );
+
+    let tile_entities = volume
+        .block_entities
+        .iter()
+        .map(|be| {
+            let mut data = be.data.clone();
+            data.insert("id".to_string(), Value::String(be.id.clone()));
+            data.insert("x".to_string(), Value::Int(be.position.x));
+            data.insert("y".to_string(), Value::Int(be.position.y));
+            data.insert("z".to_string(), Value::Int(be.position.z));
+            Value::Compound(data)
+        })
+        .collect();
+
+    let region = compound([
+        (
+            "Position",
+            compound([
+                ("x", Value::Int(0)),
+                ("y", Value::Int(0)),
+                ("z", Value::Int(0)),
+            ]),
+        ),
+        ("Size", size()),
+        (
+            "BlockStatePalette",
+            Value::List(volume.palette.iter().map(block_state_to_nbt).collect()),
+        ),
+        (
+            "BlockStates",
+            Value::LongArray(LongArray::new(block_states)),
+        ),
+        ("TileEntities", Value::List(tile_entities)),
+        ("Entities", Value::List(Vec::new())),
+        ("PendingBlockTicks", Value::List(Vec::new())),
+        ("PendingFluidTicks", Value::List(Vec::new())),
+    ]);
+
+    compound([
+        ("MinecraftDataVersion", Value::Int(volume.data_version)),
+        ("Version", Value::Int(6)),
+        (
+            "Metadata",
+            compound([
+                ("Name", Value::String(name.to_string())),
+                ("Author", Value::String("chunkmap".to_string())),
+                ("Description", Value::String(String::new())),
+                ("RegionCount", Value::Int(1)),
+                ("TotalBlocks", Value::Int(total_blocks as i32)),
+                ("TotalVolume", Value::Int(volume.volume() as i32)),
+                ("EnclosingSize", size()),
+                ("TimeCreated", Value::Long(timestamp)),
+                ("TimeModified", Value::Long(timestamp)),
+            ]),
+        ),
+        ("Regions", compound([(name, region)])),
+    ])
+}
+
+/// Read a Sponge schematic (`.schem`, versions 1 to 3) or a vanilla structure file (`.nbt`).
+/// The data can be gzip compressed or not
+pub fn read_schematic(data: &[u8]) -> Result<BlockVolume, Box<dyn Error>> {
+    let nbt: Value = if data.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut decompressed)?;
+        fastnbt::from_bytes(&decompressed)?
+    } else {
+        fastnbt::from_bytes(data)?
+    };
+
+    let Value::Compound(root) = &nbt else {
+        return Err("Root NBT is not a compound".into());
+    };
+
+    if let Some(Value::Compound(schematic)) = root.get("Schematic") {
+        Ok(parse_sponge_schematic(schematic)?)
+    } else if root.contains_key("BlockData") {
+        Ok(parse_sponge_schematic(root)?)
+    } else if root.contains_key("blocks") {
+        Ok(parse_structure_nbt(root)?)
+    } else {
+        Err("Unknown schematic format".into())
+    }
+}
+
+fn get_int(compound: &Compound, key: &str) -> Result<i32, String> {
+    match compound.get(key) {
+        Some(Value::Int(v)) => Ok(*v),
+        v => Err(format!("'{key}' not found or not an Int. Got {v:?}")),
+    }
+}
+
+/// Sizes of Sponge schematics are unsigned shorts
+fn get_size(compound: &Compound, key: &str) -> Result<usize, String> {
+    match compound.get(key) {
+        Some(Value::Short(v)) => Ok(*v as u16 as usize),
+        v => Err(format!("'{key}' not found or not a Short. Got {v:?}")),
+    }
+}
+
+fn read_varints(bytes: &[i8], count: usize) -> Result<Vec<usize>, String> {
+    let mut values = Vec::with_capacity(count);
+    let mut value = 0;
+    let mut shift = 0;
+
+    for &byte in bytes {
+        value |= ((byte as u8 & 0x7F) as usize) << shift;
+
+        if byte as u8 & 0x80 == 0 {
+            values.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+            if shift > 35 {
+                return Err("Invalid varint in schematic data".into());
+            }
+        }
+    }
+
+    if values.len() != count {
+        return Err(format!(
+            "Expected {count} values in schematic data, got {}",
+            values.len()
+        ));
+    }
+
+    Ok(values)
+}
+
+/// Sponge palettes map a block state or biome string to an index
+fn parse_sponge_palette(palette: &Compound) -> Result<Vec<String>, String> {
+    let mut entries = vec![String::new(); palette.len()];
+
+    for (name, index) in palette {
+        match index {
+            Value::Int(i) if (*i as usize) < entries.len() => entries[*i as usize] = name.clone(),
+            v => return Err(format!("Invalid palette index for '{name}'. Got {v:?}")),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn parse_sponge_schematic(schematic: &Compound) -> Result<BlockVolume, String> {
+    let version = get_int(schematic, "Version")?;
+    let size_x = get_size(schematic, "Width")?;
+    let size_y = get_size(schematic, "Height")?;
+    let size_z = get_size(schematic, "Length")?;
+    let volume_size = size_x * size_y * size_z;
+
+    // Version 3 groups the blocks and the biomes in their own compounds
+    let (blocks_compound, palette_key, data_key) = if version >= 3 {
+        match schematic.get("Blocks") {
+            Some(Value::Compound(v)) => (Some(v), "Palette", "Data"),
+            None => (None, "Palette", "Data"),
+            v => return Err(format!("'Blocks' is not a Compound. Got {v:?}")),
+        }
+    } else {
+        (Some(schematic), "Palette", "BlockData")
+    };
+
+    let (palette, blocks, block_entities) = match blocks_compound {
+        Some(compound) => {
+            let palette = match compound.get(palette_key) {
+                Some(Value::Compound(v)) => parse_sponge_palette(v)?
+                    .iter()
+                    .map(|v| v.parse::<BlockQuery>())
+                    .map(|q| {
+                        q.map(|q| BlockState {
+                            name: q.name,
+                            properties: q.properties,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                v => {
+                    return Err(format!(
+                        "'{palette_key}' not found or not a Compound. Got {v:?}"
+                    ))
+                }
+            };
+
+            let blocks = match compound.get(data_key) {
+                Some(Value::ByteArray(v)) => read_varints(v, volume_size)?,
+                v => {
+                    return Err(format!(
+                        "'{data_key}' not found or not a ByteArray. Got {v:?}"
+                    ))
+                }
+            };
+
+            let block_entities = match compound
+                .get("BlockEntities")
+                .or_else(|| compound.get("TileEntities"))
+            {
+                Some(Value::List(v)) => v
+                    .iter()
+                    .map(|v| parse_sponge_block_entity(v, version))
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => Vec::new(),
+                v => return Err(format!("'BlockEntities' is not a List. Got {v:?}")),
+            };
+
+            (palette, blocks, block_entities)
+        }
+        None => (vec![BlockState::air()], vec![0; volume_size], Vec::new()),
+    };
+
+    if let Some(&index) = blocks.iter().find(|&&i| i >= palette.len()) {
+        return Err(format!("Block palette index {index} out of range"));
+    }
+
+    let biomes = if version >= 3 {
+        match schematic.get("Biomes") {
+            Some(Value::Compound(compound)) => {
+                let palette = match compound.get("Palette") {
+                    Some(Value::Compound(v)) => parse_sponge_palette(v)?,
+                    v => {
+                        return Err(format!(
+                            "'Biomes.Palette' not found or not a Compound. Got {v:?}"
+                        ))
+                    }
+                };
+                let indices = match compound.get("Data") {
+                    Some(Value::ByteArray(v)) => read_varints(v, volume_size)?,
+                    v => {
+                        return Err(format!(
+                            "'Biomes.Data' not found or not a ByteArray. Got {v:?}"
+                        ))
+                    }
+                };
+                Some(VolumeBiomes { palette, indices })
+            }
+            _ => None,
+        }
+    } else {
+        // Version 2 stores one biome per column
+        match (schematic.get("BiomePalette"), schematic.get("BiomeData")) {
+            (Some(Value::Compound(palette)), Some(Value::ByteArray(data))) => {
+                let columns = read_varints(data, size_x * size_z)?;
+                let indices = (0..volume_size)
+                    .map(|i| columns[i % (size_x * size_z)])
+                    .collect();
+                Some(VolumeBiomes {
+                    palette: parse_sponge_palette(palette)?,
+                    indices,
+                })
+            }
+            _ => None,
+        }
+    };
+
+    if let Some(biomes) = &biomes {
+        if let Some(&index) = biomes.indices.iter().find(|&&i| i >= biomes.palette.len()) {
+            return Err(format!("Biome palette index {index} out of range"));
+        }
+    }
+
+    Ok(BlockVolume {
+        size_x,
+        size_y,
+        size_z,
+        data_version: get_int(schematic, "DataVersion").unwrap_or(0),
+        palette,
+        blocks,
+        biomes,
+        block_entities,
+    })
+}
+
+/// Version 3 block entities keep their data in `Data`, older versions next to `Pos` and `Id`
+fn parse_sponge_block_entity(value: &Value, version: i32) -> Result<BlockEntity, String> {
+    let Value::Compound(compound) = value else {
+        return Err(format!("Block entity is not a Compound. Got {value:?}"));
+    };
+
+    let position = match compound.get("Pos") {
+        Some(Value::IntArray(v)) if v.len() == 3 => BlockPosition {
+            x: v[0],
+            y: v[1],
+            z: v[2],
+        },
+        v => {
+            return Err(format!(
+                "'block_entity.Pos' not found or not an IntArray. Got {v:?}"
+            ))
+        }
+    };
+
+    let id = match compound.get("Id") {
+        Some(Value::String(v)) => v.clone(),
+        v => {
+            return Err(format!(
+                "'block_entity.Id' not found or not a String. Got {v:?}"
+            ))
+        }
+    };
+
+    let data = if version >= 3 {
+        match compound.get("Data") {
+            Some(Value::Compound(v)) => v.clone(),
+            _ => Compound::new(),
+        }
+    } else {
+        let mut data = compound.clone();
+        for key in ["Pos", "Id", "ContentVersion"] {
+            data.remove(key);
+        }
+        data
+    };
+
+    Ok(BlockEntity { id, position, data })
+}
+
+fn parse_structure_nbt(root: &Compound) -> Result<BlockVolume, String> {
+    let size = match root.get("size") {
+        Some(Value::List(v)) => v
+            .iter()
+            .map(|v| match v {
+                Value::Int(v) if *v >= 0 => Ok(*v as usize),
+                v => Err(format!("Invalid structure size. Got {v:?}")),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        v => return Err(format!("'size' not found or not a List. Got {v:?}")),
+    };
+    let [size_x, size_y, size_z] = size[..] else {
+        return Err(format!("'size' should have 3 values. Got {size:?}"));
+    };
+
+    // Structures with several palettes (e.g. shipwrecks) use the first one
+    let palette_list = match (root.get("palette"), root.get("palettes")) {
+        (Some(Value::List(v)), _) => v,
+        (None, Some(Value::List(palettes))) => match palettes.first() {
+            Some(Value::List(v)) => v,
+            v => return Err(format!("'palettes' does not contain a List. Got {v:?}")),
+        },
+        (v, _) => return Err(format!("'palette' not found or not a List. Got {v:?}")),
+    };
+
+    let mut palette = palette_list
+        .iter()
+        .map(|v| match v {
+            Value::Compound(v) => parse_block_state(v),
+            v => Err(format!("Palette entry is not a Compound. Got {v:?}")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Positions without a block are left untouched when pasting
+    let void_index = palette.len();
+    palette.push(BlockState::new(STRUCTURE_VOID));
+
+    let mut volume = BlockVolume {
+        size_x,
+        size_y,
+        size_z,
+        data_version: get_int(root, "DataVersion").unwrap_or(0),
+        palette,
+        blocks: vec![void_index; size_x * size_y * size_z],
+        biomes: None,
+        block_entities: Vec::new(),
+    };
+
+    let blocks = match root.get("blocks") {
+        Some(Value::List(v)) => v,
+        v => return Err(format!("'blocks' not found or not a List. Got {v:?}")),
+    };
+
+    for block in blocks {
+        let Value::Compound(block) = block else {
+            return Err(format!("Structure block is not a Compound. Got {block:?}"));
+        };
+
+        let state = get_int(block, "state")? as usize;
+        if state >= void_index {
+            return Err(format!("Block palette index {state} out of range"));
+        }
+
+        let position = match block.get("pos") {
+            Some(Value::List(v)) => match v[..] {
+                [Value::Int(x), Value::Int(y), Value::Int(z)] => (x, y, z),
+                _ => return Err(format!("Invalid block position. Got {v:?}")),
+            },
+            v => return Err(format!("'pos' not found or not a List. Got {v:?}")),
+        };
+        let (x, y, z) = position;
+
+        if x < 0
+            || y < 0
+            || z < 0
+            || x as usize >= size_x
+            || y as usize >= size_y
+            || z as usize >= size_z
+        {
+            return Err(format!(
+                "Block position {x} {y} {z} is outside of the structure"
+            ));
+        }
+
+        let index = volume.index(x as usize, y as usize, z as usize);
+        volume.blocks[index] = state;
+
+        if let Some(Value::Compound(nbt)) = block.get("nbt") {
+            let id = match nbt.get("id") {
+                Some(Value::String(v)) => v.clone(),
+                v => return Err(format!("'nbt.id' not found or not a String. Got {v:?}")),
+            };
+
+            let mut data = nbt.clone();
+            for key in ["id", "x", "y", "z"] {
+                data.remove(key);
+            }
+
+            volume.block_entities.push(BlockEntity {
+                id,
+                position: BlockPosition { x, y, z },
+                data,
+            });
+        }
+    }
+
+    Ok(volume)
+}
+
+/// Paste a volume with its lower corner at `origin`, replacing the blocks, biomes and
+/// block entities of the area. Structure voids are skipped, as well as air if `skip_air` is set.
+/// Only existing chunks are modified. Returns the number of modified chunks
+pub fn paste_volume(
+    world: &World,
+    dimension: &Dimension,
+    volume: &BlockVolume,
+    origin: &BlockPosition,
+    skip_air: bool,
+    timestamp: u32,
+) -> Result<usize, Box<dyn Error>> {
+    let area = BoundingBox {
+        min_x: origin.x,
+        min_y: origin.y,
+        min_z: origin.z,
+        max_x: origin.x + volume.size_x as i32 - 1,
+        max_y: origin.y + volume.size_y as i32 - 1,
+        max_z: origin.z + volume.size_z as i32 - 1,
+    };
+
+    let skipped: Vec<bool> = volume
+        .palette
+        .iter()
+        .map(|b| b.name == STRUCTURE_VOID || (skip_air && b.is_air()))
+        .collect();
+    let is_pasted = |x: i32, y: i32, z: i32| {
+        let index = volume.index(
+            (x - area.min_x) as usize,
+            (y - area.min_y) as usize,
+            (z - area.min_z) as usize,
+        );
+        !skipped[volume.blocks[index]]
+    };
+
+    edit_world_area(world, dimension, &area, timestamp, |editor| {
+        let chunk_area = editor.bounding_box();
+        if !chunk_area.intersects(&area) {
+            return Ok(());
+        }
+
+        let min_x = area.min_x.max(chunk_area.min_x);
+        let max_x = area.max_x.min(chunk_area.max_x);
+        let min_y = area.min_y.max(chunk_area.min_y);
+        let max_y = area.max_y.min(chunk_area.max_y);
+        let min_z = area.min_z.max(chunk_area.min_z);
+        let max_z = area.max_z.min(chunk_area.max_z);
+
+        editor.remove_block_entities(|be| {
+            let p = &be.position;
+            p.x >= min_x
+                && p.x <= max_x
+                && p.y >= min_y
+                && p.y <= max_y
+                && p.z >= min_z
+                && p.z <= max_z
+                && is_pasted(p.x, p.y, p.z)
+        })?;
+
+        for y in min_y..=max_y {
+            for z in min_z..=max_z {
+                for x in min_x..=max_x {
+                    if !is_pasted(x, y, z) {
+                        continue;
+                    }
+
+                    let index = volume.index(
+                        (x - area.min_x) as usize,
+                        (y - area.min_y) as usize,
+                        (z - area.min_z) as usize,
+                    );
+                    let (local_x, local_z) = (x.rem_euclid(16) as usize, z.rem_euclid(16) as usize);
+
+                    editor.set_block(local_x, y, local_z, &volume.palette[volume.blocks[index]])?;
+
+                    if let Some(biomes) = &volume.biomes {
+                        editor.set_biome(
+                            local_x,
+                            y,
+                            local_z,
+                            &biomes.palette[biomes.indices[index]],
+                        )?;
+                    }
+                }
+            }
+        }
+
+        for be in &volume.block_entities {
+            let position = BlockPosition {
+                x: be.position.x + area.min_x,
+                y: be.position.y + area.min_y,
+                z: be.position.z + area.min_z,
+            };
+
+            if position.x < min_x
+                || position.x > max_x
+                || position.y < min_y
+                || position.y > max_y
+                || position.z < min_z
+                || position.z > max_z
+            {
+                continue;
+            }
+
+            editor.set_block_entity(&BlockEntity {
+                id: be.id.clone(),
+                position,
+                data: be.data.clone(),
+            })?;
+        }
+
+        Ok(())
+    })
+}
@@ -0,0 +1,207 @@
+use crate::{
+    blocks::{BlockPosition, BlockState},
+    chunks::{parse_chunk_sections, Chunk, ChunkPosition},
+    regions::Region,
+    sections::{decode_block_indices, parse_block_palette},
+    worlds::{RegionFileInfo, World},
+};
+use fastnbt::Value;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    error::Error,
+    str::FromStr,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockMatch {
+    pub position: BlockPosition,
+    pub chunk: ChunkPosition,
+    pub block: BlockState,
+}
+
+/// A block name with the properties it must have, e.g. `minecraft:furnace[lit=true]`.
+/// Names without a namespace are considered to be in `minecraft:`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockQuery {
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl BlockQuery {
+    pub fn matches(&self, block: &BlockState) -> bool {
+        block.name == self.name
+            && self
+                .properties
+                .iter()
+                .all(|(k, v)| block.properties.get(k) == Some(v))
+    }
+}
+
+impl FromStr for BlockQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let (name, props) = match s.split_once('[') {
+            Some((name, rest)) => match rest.strip_suffix(']') {
+                Some(props) => (name, props),
+                None => return Err(format!("Missing ']' in block query '{s}'")),
+            },
+            None => (s, ""),
+        };
+
+        if name.is_empty() {
+            return Err(format!("Missing block name in block query '{s}'"));
+        }
+
+        let name = if name.contains(':') {
+            name.to_string()
+        } else {
+            format!("minecraft:{name}")
+        };
+
+        let mut properties = BTreeMap::new();
+
+        for prop in props.split(',').filter(|p| !p.trim().is_empty()) {
+            match prop.split_once('=') {
+                Some((k, v)) => {
+                    properties.insert(k.trim().to_string(), v.trim().to_string());
+                }
+                None => return Err(format!("Invalid property '{prop}' in block query '{s}'")),
+            }
+        }
+
+        Ok(BlockQuery { name, properties })
+    }
+}
+
+/// Find the blocks of the chunk matching the predicate.
+/// Sections whose palette has no matching block are skipped without being decoded
+pub fn find_blocks_in_chunk<F>(chunk: &Chunk, predicate: &F) -> Result<Vec<BlockMatch>, String>
+where
+    F: Fn(&BlockState) -> bool,
+{
+    let root = match &chunk.nbt {
+        Value::Compound(v) => v,
+        _ => return Err("Root NBT is not a compound".into()),
+    };
+
+    let sections = parse_chunk_sections(root)?;
+    let mut section_ys: Vec<&i32> = sections.keys().collect();
+    section_ys.sort();
+
+    let mut matches = Vec::new();
+
+    for section_y in section_ys {
+        let section = sections[section_y];
+
+        // Sections above the world can exist with only light data
+        if !section.contains_key("block_states") {
+            continue;
+        }
+
+        let palette = parse_block_palette(section)?;
+        let matching: Vec<bool> = palette.iter().map(predicate).collect();
+
+        if !matching.contains(&true) {
+            continue;
+        }
+
+        let indices = decode_block_indices(section, palette.len())?;
+
+        for (i, &palette_index) in indices.iter().enumerate() {
+            if !matching[palette_index] {
+                continue;
+            }
+
+            // YZX order
+            let local_x = (i % 16) as i32;
+            let local_z = ((i / 16) % 16) as i32;
+            let local_y = (i / 256) as i32;
+
+            matches.push(BlockMatch {
+                position: BlockPosition {
+                    x: chunk.position.x * 16 + local_x,
+                    y: section_y * 16 + local_y,
+                    z: chunk.position.z * 16 + local_z,
+                },
+                chunk: chunk.position.clone(),
+                block: palette[palette_index].clone(),
+            });
+        }
+    }
+
+    Ok(matches)
+}
+
+pub fn find_blocks_in_region<F>(region: &Region, predicate: &F) -> Result<Vec<BlockMatch>, String>
+where
+    F: Fn(&BlockState) -> bool,
+{
+    let mut matches = Vec::new();
+
+    for chunk in &region.chunks {
+        matches.extend(find_blocks_in_chunk(chunk, predicate)?);
+    }
+
+    Ok(matches)
+}
+
+/// Iterator over the matching blocks of a world, reading one region file at a time.
+/// A region that cannot be read or parsed yields an error, then the search continues
+pub struct BlockSearch<F> {
+    world: World,
+    regions: VecDeque<RegionFileInfo>,
+    pending: VecDeque<BlockMatch>,
+    predicate: F,
+}
+
+impl<F> Iterator for BlockSearch<F>
+where
+    F: Fn(&BlockState) -> bool,
+{
+    type Item = Result<BlockMatch, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(block) = self.pending.pop_front() {
+                return Some(Ok(block));
+            }
+
+            let info = self.regions.pop_front()?;
+
+            let region = match self.world.read_region_file(&info) {
+                Ok(v) => v,
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            match find_blocks_in_region(&region, &self.predicate) {
+                Ok(matches) => self.pending.extend(matches),
+                Err(e) => return Some(Err(format!("Region {}.{}: {e}", info.x, info.z).into())),
+            }
+        }
+    }
+}
+
+/// Search the whole world for the blocks matching the predicate
+pub fn search_blocks<F>(world: &World, predicate: F) -> BlockSearch<F>
+where
+    F: Fn(&BlockState) -> bool,
+{
+    BlockSearch {
+        world: world.clone(),
+        regions: world.regions.iter().cloned().collect(),
+        pending: VecDeque::new(),
+        predicate,
+    }
+}
+
+/// Search the whole world for the blocks matching any of the queries
+pub fn search_block_queries(
+    world: &World,
+    queries: Vec<BlockQuery>,
+) -> BlockSearch<impl Fn(&BlockState) -> bool> {
+    search_blocks(world, move |block| queries.iter().any(|q| q.matches(block)))
+}
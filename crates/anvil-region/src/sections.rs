@@ -1,6 +1,11 @@
-use crate::nbt::Compound;
+use crate::{blocks::BlockState, nbt::Compound};
 use fastnbt::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// Number of blocks in a section (16x16x16)
+pub const SECTION_BLOCK_COUNT: usize = 4096;
+/// Number of biome cells in a section (4x4x4)
+pub const SECTION_BIOME_COUNT: usize = 64;
 
 pub fn get_biome_at_position(
     section: &Compound,
@@ -11,18 +16,18 @@ pub fn get_biome_at_position(
     let biomes = match section.get("biomes") {
         Some(Value::Compound(v)) => v,
         v => {
-            return Err(
-                format!("'biomes' not found in section or not a Compound. Got {v:?}").into(),
-            )
+            return Err(format!(
+                "'biomes' not found in section or not a Compound. Got {v:?}"
+            ))
         }
     };
 
     let palette = match biomes.get("palette") {
         Some(Value::List(v)) => v,
         v => {
-            return Err(
-                format!("'biomes.palette' not found in section or not a List. Got {v:?}").into(),
-            )
+            return Err(format!(
+                "'biomes.palette' not found in section or not a List. Got {v:?}"
+            ))
         }
     };
 
@@ -32,9 +37,9 @@ pub fn get_biome_at_position(
         return match &palette[0] {
             Value::String(name) => Ok(name.clone()),
             v => {
-                return Err(
-                    format!("The biome name in the palette is not a String. Got {v:?}").into(),
-                )
+                return Err(format!(
+                    "The biome name in the palette is not a String. Got {v:?}"
+                ))
             }
         };
     }
@@ -43,31 +48,32 @@ pub fn get_biome_at_position(
 
     let data = match biomes.get("data") {
         Some(Value::LongArray(v)) => v,
-        v => return Err(format!("'biomes.data' not found or not a LongArray. Got {v:?}").into()),
+        v => {
+            return Err(format!(
+                "'biomes.data' not found or not a LongArray. Got {v:?}"
+            ))
+        }
     };
 
     let cell_x = x / 4;
     let cell_y = y / 4;
     let cell_z = z / 4;
     let biome_index = (cell_y * 4 + cell_z) * 4 + cell_x; // Y-Z-X, 4x4x4 = 64
-    let bits_per_entry = (palette.len() as f64).log2().ceil() as usize;
+    let bits_per_entry = calculate_biome_bits_per_entry(palette.len());
     let palette_index = extract_palette_index(data, biome_index, bits_per_entry)?;
 
     if palette_index >= palette.len() {
         return Err(format!(
             "Invalid biome palette index: got {}, palette size is {}, bits_per_entry is {}, biome_index is {}",
             palette_index, palette.len(), bits_per_entry, biome_index
-        ).into());
+        ));
     }
 
     match &palette[palette_index] {
         Value::String(name) => Ok(name.clone()),
-        v => {
-            return Err(format!(
-                "The biome name at index {palette_index} in the palette is not a String. Got {v:?}"
-            )
-            .into())
-        }
+        v => Err(format!(
+            "The biome name at index {palette_index} in the palette is not a String. Got {v:?}"
+        )),
     }
 }
 
@@ -80,9 +86,9 @@ pub fn get_block_at_position(
     let block_states = match section.get("block_states") {
         Some(Value::Compound(v)) => v,
         v => {
-            return Err(
-                format!("'block_states' not found in section or not a Compound. Got {v:?}").into(),
-            )
+            return Err(format!(
+                "'block_states' not found in section or not a Compound. Got {v:?}"
+            ))
         }
     };
 
@@ -91,8 +97,7 @@ pub fn get_block_at_position(
         v => {
             return Err(format!(
                 "'block_states.palette' not found in section or not a List. Got {v:?}"
-            )
-            .into())
+            ))
         }
     };
 
@@ -101,10 +106,10 @@ pub fn get_block_at_position(
     if palette.len() == 1 {
         let block = match &palette[0] {
             Value::Compound(v) => v,
-            v => return Err(format!("'palette[0]' is not a Compound. Got {v:?}").into()),
+            v => return Err(format!("'palette[0]' is not a Compound. Got {v:?}")),
         };
 
-        return Ok(extract_block_data(block)?);
+        return extract_block_data(block);
     }
 
     // Otherwise we calculate the palette index
@@ -122,15 +127,19 @@ pub fn get_block_at_position(
         return Err(format!(
             "Invalid palette index: got {}, palette size is {}, bits_per_entry is {}, block_index is {}", 
             palette_index, palette.len(), bits_per_entry, block_index
-        ).into());
+        ));
     }
 
     let block = match &palette[palette_index] {
         Value::Compound(v) => v,
-        v => return Err(format!("'palette[palette_index]' is not a Compound. Got {v:?}").into()),
+        v => {
+            return Err(format!(
+                "'palette[palette_index]' is not a Compound. Got {v:?}"
+            ))
+        }
     };
 
-    Ok(extract_block_data(block)?)
+    extract_block_data(block)
 }
 
 pub fn extract_block_data(
@@ -140,9 +149,9 @@ pub fn extract_block_data(
         Some(Value::Compound(props)) => Some(props),
         None => None,
         v => {
-            return Err(
-                format!("'block.Properties' was found but is not Compound. Got {v:?}").into(),
-            )
+            return Err(format!(
+                "'block.Properties' was found but is not Compound. Got {v:?}"
+            ))
         }
     };
 
@@ -151,26 +160,174 @@ pub fn extract_block_data(
 
     if let Some(c) = properties {
         if c.contains_key("snowy") {
-            match c.get("snowy") {
-                Some(Value::String(snowy)) => {
-                    let mut map = HashMap::new();
+            if let Some(Value::String(snowy)) = c.get("snowy") {
+                let mut map = HashMap::new();
 
-                    map.insert(String::from("snowy"), snowy.clone());
-                    block_props = Some(map);
-                }
-                _ => {}
+                map.insert(String::from("snowy"), snowy.clone());
+                block_props = Some(map);
             };
         }
     }
 
     let block_name = match block.get("Name") {
         Some(Value::String(name)) => name.clone(),
-        v => return Err(format!("'block.Name' not found or not a String. Got {v:?}").into()),
+        v => return Err(format!("'block.Name' not found or not a String. Got {v:?}")),
     };
 
     Ok((block_name, block_props))
 }
 
+/// Parse the whole block palette of a section, keeping all the properties
+pub fn parse_block_palette(section: &Compound) -> Result<Vec<BlockState>, String> {
+    let block_states = match section.get("block_states") {
+        Some(Value::Compound(v)) => v,
+        v => {
+            return Err(format!(
+                "'block_states' not found in section or not a Compound. Got {v:?}"
+            ))
+        }
+    };
+
+    let palette = match block_states.get("palette") {
+        Some(Value::List(v)) => v,
+        v => {
+            return Err(format!(
+                "'block_states.palette' not found in section or not a List. Got {v:?}"
+            ))
+        }
+    };
+
+    palette
+        .iter()
+        .map(|entry| match entry {
+            Value::Compound(block) => parse_block_state(block),
+            v => Err(format!("'palette' entry is not a Compound. Got {v:?}")),
+        })
+        .collect()
+}
+
+pub fn parse_block_state(block: &Compound) -> Result<BlockState, String> {
+    let name = match block.get("Name") {
+        Some(Value::String(name)) => name.clone(),
+        v => return Err(format!("'block.Name' not found or not a String. Got {v:?}")),
+    };
+
+    let mut properties = BTreeMap::new();
+
+    match block.get("Properties") {
+        Some(Value::Compound(props)) => {
+            for (key, value) in props {
+                if let Value::String(value) = value {
+                    properties.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        None => {}
+        v => {
+            return Err(format!(
+                "'block.Properties' was found but is not Compound. Got {v:?}"
+            ))
+        }
+    }
+
+    Ok(BlockState { name, properties })
+}
+
+/// Decode the palette index of every block of a section, stored in YZX order
+pub fn decode_block_indices(section: &Compound, palette_size: usize) -> Result<Vec<usize>, String> {
+    if palette_size <= 1 {
+        return Ok(vec![0; SECTION_BLOCK_COUNT]);
+    }
+
+    let data = match section.get("block_states") {
+        Some(Value::Compound(block_states)) => match block_states.get("data") {
+            Some(Value::LongArray(array)) => array,
+            _ => return Err("block data not found or not a long array".into()),
+        },
+        v => {
+            return Err(format!(
+                "'block_states' not found in section or not a Compound. Got {v:?}"
+            ))
+        }
+    };
+
+    let bits_per_entry = calculate_bits_per_entry(palette_size);
+    let indices = unpack_palette_indices(data, SECTION_BLOCK_COUNT, bits_per_entry)?;
+
+    if let Some(index) = indices.iter().find(|&&i| i >= palette_size) {
+        return Err(format!(
+            "Invalid palette index: got {index}, palette size is {palette_size}"
+        ));
+    }
+
+    Ok(indices)
+}
+
+/// Parse the biome palette of a section
+pub fn parse_biome_palette(section: &Compound) -> Result<Vec<String>, String> {
+    let biomes = match section.get("biomes") {
+        Some(Value::Compound(v)) => v,
+        v => {
+            return Err(format!(
+                "'biomes' not found in section or not a Compound. Got {v:?}"
+            ))
+        }
+    };
+
+    let palette = match biomes.get("palette") {
+        Some(Value::List(v)) => v,
+        v => {
+            return Err(format!(
+                "'biomes.palette' not found in section or not a List. Got {v:?}"
+            ))
+        }
+    };
+
+    palette
+        .iter()
+        .map(|entry| match entry {
+            Value::String(name) => Ok(name.clone()),
+            v => Err(format!(
+                "The biome name in the palette is not a String. Got {v:?}"
+            )),
+        })
+        .collect()
+}
+
+/// Decode the palette index of every biome cell of a section, stored in YZX order
+pub fn decode_biome_indices(section: &Compound, palette_size: usize) -> Result<Vec<usize>, String> {
+    if palette_size <= 1 {
+        return Ok(vec![0; SECTION_BIOME_COUNT]);
+    }
+
+    let data = match section.get("biomes") {
+        Some(Value::Compound(biomes)) => match biomes.get("data") {
+            Some(Value::LongArray(v)) => v,
+            v => {
+                return Err(format!(
+                    "'biomes.data' not found or not a LongArray. Got {v:?}"
+                ))
+            }
+        },
+        v => {
+            return Err(format!(
+                "'biomes' not found in section or not a Compound. Got {v:?}"
+            ))
+        }
+    };
+
+    let bits_per_entry = calculate_biome_bits_per_entry(palette_size);
+    let indices = unpack_palette_indices(data, SECTION_BIOME_COUNT, bits_per_entry)?;
+
+    if let Some(index) = indices.iter().find(|&&i| i >= palette_size) {
+        return Err(format!(
+            "Invalid biome palette index: got {index}, palette size is {palette_size}"
+        ));
+    }
+
+    Ok(indices)
+}
+
 /// Calculate the bits per entry in the palette array.
 /// Check the wiki for more info
 pub fn calculate_bits_per_entry(palette_size: usize) -> usize {
@@ -205,8 +362,7 @@ pub fn extract_palette_index(
             "Long index {} out of bounds (data length: {})",
             long_index,
             data.len()
-        )
-        .into());
+        ));
     }
 
     let long_value = data[long_index] as u64;
@@ -216,3 +372,69 @@ pub fn extract_palette_index(
 
     Ok(palette_index as usize)
 }
+
+/// Unpack `count` palette indices at once.
+/// Like `extract_palette_index`, entries never span two longs
+pub fn unpack_palette_indices(
+    data: &[i64],
+    count: usize,
+    bits_per_entry: usize,
+) -> Result<Vec<usize>, String> {
+    if bits_per_entry == 0 {
+        return Ok(vec![0; count]);
+    }
+
+    let entries_per_long = 64 / bits_per_entry;
+    let required_longs = count.div_ceil(entries_per_long);
+
+    if data.len() < required_longs {
+        return Err(format!(
+            "Data too short: {required_longs} longs required, got {}",
+            data.len()
+        ));
+    }
+
+    let mask = (1u64 << bits_per_entry) - 1;
+    let mut indices = Vec::with_capacity(count);
+
+    for &long_value in &data[..required_longs] {
+        let long_value = long_value as u64;
+
+        for entry_index in 0..entries_per_long {
+            if indices.len() == count {
+                break;
+            }
+            let shift = entry_index * bits_per_entry;
+            indices.push(((long_value >> shift) & mask) as usize);
+        }
+    }
+
+    Ok(indices)
+}
+
+/// Pack palette indices into longs, the inverse of `unpack_palette_indices`
+pub fn pack_palette_indices(indices: &[usize], bits_per_entry: usize) -> Vec<i64> {
+    if bits_per_entry == 0 {
+        return Vec::new();
+    }
+
+    let entries_per_long = 64 / bits_per_entry;
+    let mut data = vec![0u64; indices.len().div_ceil(entries_per_long)];
+
+    for (i, &palette_index) in indices.iter().enumerate() {
+        let shift = (i % entries_per_long) * bits_per_entry;
+        data[i / entries_per_long] |= (palette_index as u64) << shift;
+    }
+
+    data.into_iter().map(|v| v as i64).collect()
+}
+
+/// Calculate the bits per entry in the biome palette array.
+/// Unlike blocks, there is no minimum
+pub fn calculate_biome_bits_per_entry(palette_size: usize) -> usize {
+    if palette_size <= 1 {
+        return 0;
+    }
+
+    (palette_size as f64).log2().ceil() as usize
+}
@@ -0,0 +1,137 @@
+use crate::{
+    chunks::{parse_chunk_sections, Chunk},
+    regions::Region,
+    sections::{
+        decode_biome_indices, decode_block_indices, parse_biome_palette, parse_block_palette,
+        SECTION_BLOCK_COUNT,
+    },
+};
+use fastnbt::Value;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Number of blocks of each block and biome type
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BlockStats {
+    pub blocks: BTreeMap<String, u64>,
+    /// Biomes are counted in blocks, a biome cell being 4x4x4 blocks
+    pub biomes: BTreeMap<String, u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub blocks_by_y: BTreeMap<i32, BTreeMap<String, u64>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub biomes_by_y: BTreeMap<i32, BTreeMap<String, u64>>,
+}
+
+impl BlockStats {
+    /// Add the counts of other stats to these ones
+    pub fn merge(&mut self, other: &BlockStats) {
+        merge_counts(&mut self.blocks, &other.blocks);
+        merge_counts(&mut self.biomes, &other.biomes);
+
+        for (y, counts) in &other.blocks_by_y {
+            merge_counts(self.blocks_by_y.entry(*y).or_default(), counts);
+        }
+        for (y, counts) in &other.biomes_by_y {
+            merge_counts(self.biomes_by_y.entry(*y).or_default(), counts);
+        }
+    }
+
+    /// Total number of blocks counted
+    pub fn total_blocks(&self) -> u64 {
+        self.blocks.values().sum()
+    }
+}
+
+fn merge_counts(counts: &mut BTreeMap<String, u64>, other: &BTreeMap<String, u64>) {
+    for (name, count) in other {
+        *counts.entry(name.clone()).or_default() += count;
+    }
+}
+
+/// Count every block and biome of the chunk, using the section palettes.
+/// The counts by Y level are only computed if `by_y` is set
+pub fn chunk_stats(chunk: &Chunk, by_y: bool) -> Result<BlockStats, String> {
+    let root = match &chunk.nbt {
+        Value::Compound(v) => v,
+        _ => return Err("Root NBT is not a compound".into()),
+    };
+
+    let sections = parse_chunk_sections(root)?;
+    let mut stats = BlockStats::default();
+
+    for (section_y, section) in sections {
+        // Sections above the world can exist with only light data
+        if !section.contains_key("block_states") {
+            continue;
+        }
+
+        // Count palette indices per level, or for the whole section, before resolving names
+        let palette = parse_block_palette(section)?;
+        let indices = decode_block_indices(section, palette.len())?;
+        let levels = if by_y { 16 } else { 1 };
+        let mut counts = vec![vec![0u64; palette.len()]; levels];
+
+        for (i, &palette_index) in indices.iter().enumerate() {
+            counts[i * levels / SECTION_BLOCK_COUNT][palette_index] += 1;
+        }
+
+        for (local_y, level_counts) in counts.iter().enumerate() {
+            let mut level = by_y.then(|| {
+                stats
+                    .blocks_by_y
+                    .entry(section_y * 16 + local_y as i32)
+                    .or_default()
+            });
+
+            for (palette_index, &count) in level_counts.iter().enumerate() {
+                if count == 0 {
+                    continue;
+                }
+                let name = &palette[palette_index].name;
+                if let Some(level) = level.as_mut() {
+                    *level.entry(name.clone()).or_default() += count;
+                }
+                *stats.blocks.entry(name.clone()).or_default() += count;
+            }
+        }
+
+        if !section.contains_key("biomes") {
+            continue;
+        }
+
+        let biome_palette = parse_biome_palette(section)?;
+        let biome_indices = decode_biome_indices(section, biome_palette.len())?;
+
+        for (i, &palette_index) in biome_indices.iter().enumerate() {
+            let name = &biome_palette[palette_index];
+
+            if by_y {
+                // A cell covers 4 levels of 4x4 blocks
+                let cell_y = section_y * 16 + (i / 16) as i32 * 4;
+
+                for y in cell_y..cell_y + 4 {
+                    *stats
+                        .biomes_by_y
+                        .entry(y)
+                        .or_default()
+                        .entry(name.clone())
+                        .or_default() += 16;
+                }
+            }
+            *stats.biomes.entry(name.clone()).or_default() += 64;
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Count every block and biome of the region, see `chunk_stats`
+pub fn region_stats(region: &Region, by_y: bool) -> Result<BlockStats, String> {
+    let mut stats = BlockStats::default();
+
+    for chunk in &region.chunks {
+        stats.merge(&chunk_stats(chunk, by_y)?);
+    }
+
+    Ok(stats)
+}
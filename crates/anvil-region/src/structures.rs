@@ -0,0 +1,251 @@
+use crate::{
+    blocks::BoundingBox,
+    chunks::{Chunk, ChunkPosition},
+    nbt::Compound,
+    worlds::World,
+};
+use fastnbt::Value;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    error::Error,
+};
+
+/// A piece of a generated structure, e.g. a house of a village
+#[derive(Debug, Clone, Serialize)]
+pub struct StructurePiece {
+    pub id: String,
+    pub bounding_box: BoundingBox,
+}
+
+/// A structure, stored in the chunk where its generation started
+#[derive(Debug, Clone, Serialize)]
+pub struct StructureStart {
+    /// Structure id, e.g. `minecraft:village_plains`
+    pub id: String,
+    pub chunk: ChunkPosition,
+    /// Box containing all the pieces
+    pub bounding_box: BoundingBox,
+    pub pieces: Vec<StructurePiece>,
+}
+
+/// Chunks containing the start of a structure that overlaps the current chunk
+#[derive(Debug, Clone, Serialize)]
+pub struct StructureReference {
+    pub id: String,
+    pub chunks: Vec<ChunkPosition>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChunkStructures {
+    pub starts: Vec<StructureStart>,
+    pub references: Vec<StructureReference>,
+}
+
+pub fn get_chunk_structures(chunk: &Chunk) -> Result<ChunkStructures, String> {
+    match &chunk.nbt {
+        Value::Compound(root) => parse_chunk_structures(root),
+        _ => Err("Root NBT is not a compound".into()),
+    }
+}
+
+/// Parse `structures.starts` and `structures.References` of a chunk
+pub fn parse_chunk_structures(root: &Compound) -> Result<ChunkStructures, String> {
+    let structures = match root.get("structures") {
+        Some(Value::Compound(v)) => v,
+        None => return Ok(ChunkStructures::default()),
+        v => return Err(format!("'structures' is not a Compound. Got {v:?}")),
+    };
+
+    let mut starts = Vec::new();
+
+    if let Some(Value::Compound(raw_starts)) = structures.get("starts") {
+        for (key, raw_start) in raw_starts {
+            let start = match raw_start {
+                Value::Compound(v) => v,
+                v => {
+                    return Err(format!(
+                        "Structure start '{key}' is not a Compound. Got {v:?}"
+                    ))
+                }
+            };
+
+            if let Some(start) = parse_structure_start(start)? {
+                starts.push(start);
+            }
+        }
+    }
+
+    let mut references = Vec::new();
+
+    if let Some(Value::Compound(raw_references)) = structures.get("References") {
+        for (id, packed) in raw_references {
+            let packed = match packed {
+                Value::LongArray(v) => v,
+                v => {
+                    return Err(format!(
+                        "Structure reference '{id}' is not a LongArray. Got {v:?}"
+                    ))
+                }
+            };
+
+            let chunks = packed
+                .iter()
+                .map(|&v| ChunkPosition {
+                    // X in the low 32 bits, Z in the high ones
+                    x: v as i32,
+                    z: (v >> 32) as i32,
+                })
+                .collect();
+
+            references.push(StructureReference {
+                id: id.clone(),
+                chunks,
+            });
+        }
+    }
+
+    // Keep a stable order, the NBT compounds are unordered
+    starts.sort_by(|a, b| a.id.cmp(&b.id));
+    references.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(ChunkStructures { starts, references })
+}
+
+/// Structures overlapping the chunks: the ones starting in them, and the ones starting in
+/// other chunks of the world that they reference, e.g. across a region border. Each region
+/// containing referenced starts is read once
+pub fn read_overlapping_starts(
+    world: &World,
+    chunks: &[Chunk],
+) -> Result<Vec<StructureStart>, Box<dyn Error>> {
+    let loaded: HashSet<(i32, i32)> = chunks
+        .iter()
+        .map(|c| (c.position.x, c.position.z))
+        .collect();
+
+    let mut starts = Vec::new();
+    // Structure ids referenced in each start chunk, by region x and z then chunk x and z
+    let mut referenced: BTreeMap<[i32; 4], BTreeSet<String>> = BTreeMap::new();
+
+    for chunk in chunks {
+        let structures = get_chunk_structures(chunk)?;
+        starts.extend(structures.starts);
+
+        for reference in structures.references {
+            for c in reference.chunks {
+                if loaded.contains(&(c.x, c.z)) {
+                    continue;
+                }
+                referenced
+                    .entry([c.x.div_euclid(32), c.z.div_euclid(32), c.x, c.z])
+                    .or_default()
+                    .insert(reference.id.clone());
+            }
+        }
+    }
+
+    let mut region_file = None;
+
+    for ([region_x, region_z, x, z], ids) in referenced {
+        let region_file = match &region_file {
+            Some((position, file)) if *position == (region_x, region_z) => file,
+            _ => {
+                let file = match world.region(region_x, region_z) {
+                    Some(info) => Some(world.read_region_file_raw(info)?),
+                    None => None,
+                };
+                &region_file.insert(((region_x, region_z), file)).1
+            }
+        };
+
+        let Some(region_file) = region_file else {
+            continue;
+        };
+
+        if let Some(chunk) = region_file.get_chunk(x.rem_euclid(32), z.rem_euclid(32))? {
+            let chunk_starts = get_chunk_structures(&chunk)?.starts;
+            starts.extend(chunk_starts.into_iter().filter(|s| ids.contains(&s.id)));
+        }
+    }
+
+    Ok(starts)
+}
+
+/// Returns None for the `INVALID` starts left by the game when nothing was generated
+fn parse_structure_start(start: &Compound) -> Result<Option<StructureStart>, String> {
+    let id = match start.get("id") {
+        Some(Value::String(v)) => v.clone(),
+        v => return Err(format!("'start.id' not found or not a String. Got {v:?}")),
+    };
+
+    if id == "INVALID" {
+        return Ok(None);
+    }
+
+    let chunk_x = match start.get("ChunkX") {
+        Some(Value::Int(v)) => *v,
+        v => return Err(format!("'start.ChunkX' not found or not an Int. Got {v:?}")),
+    };
+    let chunk_z = match start.get("ChunkZ") {
+        Some(Value::Int(v)) => *v,
+        v => return Err(format!("'start.ChunkZ' not found or not an Int. Got {v:?}")),
+    };
+
+    let mut pieces = Vec::new();
+
+    if let Some(Value::List(children)) = start.get("Children") {
+        for child in children {
+            let child = match child {
+                Value::Compound(v) => v,
+                v => {
+                    return Err(format!(
+                        "'start.Children' entry is not a Compound. Got {v:?}"
+                    ))
+                }
+            };
+
+            let piece_id = match child.get("id") {
+                Some(Value::String(v)) => v.clone(),
+                v => return Err(format!("'piece.id' not found or not a String. Got {v:?}")),
+            };
+
+            let bounding_box = match child.get("BB") {
+                Some(Value::IntArray(v)) => BoundingBox::from_int_array(v)?,
+                v => {
+                    return Err(format!(
+                        "'piece.BB' not found or not an IntArray. Got {v:?}"
+                    ))
+                }
+            };
+
+            pieces.push(StructurePiece {
+                id: piece_id,
+                bounding_box,
+            });
+        }
+    }
+
+    // Older versions store the box of the start, newer ones only the boxes of the pieces
+    let bounding_box = match start.get("BB") {
+        Some(Value::IntArray(v)) => BoundingBox::from_int_array(v)?,
+        _ => match pieces
+            .iter()
+            .map(|p| p.bounding_box)
+            .reduce(|a, b| a.union(&b))
+        {
+            Some(v) => v,
+            None => return Ok(None),
+        },
+    };
+
+    Ok(Some(StructureStart {
+        id,
+        chunk: ChunkPosition {
+            x: chunk_x,
+            z: chunk_z,
+        },
+        bounding_box,
+        pieces,
+    }))
+}
@@ -0,0 +1,186 @@
+use crate::blocks::BlockState;
+
+/// Clockwise rotation around the Y axis, seen from above
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    CounterClockwise90,
+}
+
+/// Mirror with the same names as the game's structure blocks
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Mirror {
+    #[default]
+    None,
+    /// Flip the Z axis (north becomes south)
+    LeftRight,
+    /// Flip the X axis (east becomes west)
+    FrontBack,
+}
+
+/// A mirror followed by a rotation, like the game applies to structures
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Transform {
+    pub mirror: Mirror,
+    pub rotation: Rotation,
+}
+
+const HORIZONTAL_DIRECTIONS: [(&str, i32, i32); 4] = [
+    ("north", 0, -1),
+    ("east", 1, 0),
+    ("south", 0, 1),
+    ("west", -1, 0),
+];
+
+impl Transform {
+    pub fn new(mirror: Mirror, rotation: Rotation) -> Transform {
+        Transform { mirror, rotation }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.mirror == Mirror::None && self.rotation == Rotation::None
+    }
+
+    /// Whether the X and Z sizes are swapped
+    pub fn swaps_axes(&self) -> bool {
+        matches!(
+            self.rotation,
+            Rotation::Clockwise90 | Rotation::CounterClockwise90
+        )
+    }
+
+    /// Transform a horizontal position relative to the origin of the transformation
+    pub fn apply(&self, x: i32, z: i32) -> (i32, i32) {
+        let (x, z) = match self.mirror {
+            Mirror::None => (x, z),
+            Mirror::LeftRight => (x, -z),
+            Mirror::FrontBack => (-x, z),
+        };
+
+        match self.rotation {
+            Rotation::None => (x, z),
+            Rotation::Clockwise90 => (-z, x),
+            Rotation::Clockwise180 => (-x, -z),
+            Rotation::CounterClockwise90 => (z, -x),
+        }
+    }
+
+    /// Transform `north`, `east`, `south` or `west`. Other values are returned as is
+    pub fn apply_direction<'a>(&self, direction: &'a str) -> &'a str {
+        let Some(&(_, dx, dz)) = HORIZONTAL_DIRECTIONS
+            .iter()
+            .find(|(name, _, _)| *name == direction)
+        else {
+            return direction;
+        };
+
+        let (dx, dz) = self.apply(dx, dz);
+
+        HORIZONTAL_DIRECTIONS
+            .iter()
+            .find(|(_, x, z)| *x == dx && *z == dz)
+            .map(|(name, _, _)| *name)
+            .unwrap_or(direction)
+    }
+
+    /// Transform the 16 directions of the `rotation` property of signs, banners and heads.
+    /// 0 is south and values increase clockwise
+    fn apply_rotation_property(&self, value: u8) -> u8 {
+        let value = match self.mirror {
+            Mirror::None => value,
+            Mirror::LeftRight => (24 - value) % 16,
+            Mirror::FrontBack => (16 - value) % 16,
+        };
+
+        let steps = match self.rotation {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 4,
+            Rotation::Clockwise180 => 8,
+            Rotation::CounterClockwise90 => 12,
+        };
+
+        (value + steps) % 16
+    }
+
+    /// Transform the directions in values like `north_east`, `ascending_west` or `up_south`.
+    /// Rail corners keep the game's order, north or south first
+    fn apply_direction_list(&self, value: &str) -> String {
+        let mut parts: Vec<&str> = value.split('_').map(|v| self.apply_direction(v)).collect();
+
+        let rank = |v: &str| {
+            ["north", "south", "east", "west"]
+                .iter()
+                .position(|d| *d == v)
+        };
+
+        if parts.len() == 2 && parts.iter().all(|v| rank(v).is_some()) {
+            parts.sort_by_key(|v| rank(v));
+        }
+
+        parts.join("_")
+    }
+
+    /// Transform the properties of a block: facing, axis, rotation, connections
+    /// (fences, walls, vines...), stairs and rail shapes, door hinges and chest halves
+    pub fn apply_block_state(&self, block: &BlockState) -> BlockState {
+        if self.is_identity() {
+            return block.clone();
+        }
+
+        let mirrored = self.mirror != Mirror::None;
+        let mut properties = block.properties.clone();
+
+        // Connections move to the transformed side
+        for direction in ["north", "east", "south", "west"] {
+            properties.remove(direction);
+        }
+
+        for (key, value) in &block.properties {
+            let new_value = match key.as_str() {
+                "facing" => self.apply_direction(value).to_string(),
+                "axis" if self.swaps_axes() => match value.as_str() {
+                    "x" => "z".to_string(),
+                    "z" => "x".to_string(),
+                    v => v.to_string(),
+                },
+                "rotation" => match value.parse::<u8>() {
+                    Ok(v) => self.apply_rotation_property(v % 16).to_string(),
+                    Err(_) => value.clone(),
+                },
+                "orientation" => self.apply_direction_list(value),
+                "shape" if value.contains("left") || value.contains("right") => {
+                    if mirrored {
+                        swap_left_right(value)
+                    } else {
+                        value.clone()
+                    }
+                }
+                "shape" => self.apply_direction_list(value),
+                "hinge" | "type" if mirrored => swap_left_right(value),
+                "north" | "east" | "south" | "west" => {
+                    properties.insert(self.apply_direction(key).to_string(), value.clone());
+                    continue;
+                }
+                _ => continue,
+            };
+
+            properties.insert(key.clone(), new_value);
+        }
+
+        BlockState {
+            name: block.name.clone(),
+            properties,
+        }
+    }
+}
+
+fn swap_left_right(value: &str) -> String {
+    if value.contains("left") {
+        value.replace("left", "right")
+    } else {
+        value.replace("right", "left")
+    }
+}
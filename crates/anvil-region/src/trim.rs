@@ -0,0 +1,309 @@
+use crate::{
+    regions::{RegionFile, RegionFormat},
+    worlds::{StagedFile, World},
+};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::{fs::remove_file, io, path::PathBuf};
+
+/// Area of the world to keep, in block coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrimShape {
+    Circle {
+        x: f64,
+        z: f64,
+        radius: f64,
+    },
+    /// Inclusive bounds
+    Rectangle {
+        min_x: i32,
+        min_z: i32,
+        max_x: i32,
+        max_z: i32,
+    },
+    /// Rings of (x, z) points, using the even-odd rule so rings inside others are holes
+    Polygon(Vec<Vec<(f64, f64)>>),
+}
+
+impl TrimShape {
+    /// Read a GeoJSON Polygon or MultiPolygon, or a Feature or FeatureCollection of them.
+    /// Coordinates are `[x, z]` block coordinates
+    pub fn from_geojson(json: &str) -> Result<TrimShape, String> {
+        let value: JsonValue =
+            serde_json::from_str(json).map_err(|e| format!("Invalid GeoJSON: {e}"))?;
+
+        let mut rings = Vec::new();
+        collect_geojson_rings(&value, &mut rings)?;
+
+        if rings.is_empty() {
+            return Err("No polygon found in the GeoJSON".into());
+        }
+
+        Ok(TrimShape::Polygon(rings))
+    }
+
+    /// Whether any block of the chunk is inside the shape. Blocks are the points at their
+    /// coordinates, like the inclusive bounds of the rectangles
+    pub fn contains_chunk(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        let min_x = chunk_x as f64 * 16.0;
+        let min_z = chunk_z as f64 * 16.0;
+        let max_x = min_x + 15.0;
+        let max_z = min_z + 15.0;
+
+        match self {
+            TrimShape::Circle { x, z, radius } => {
+                let dx = x - x.clamp(min_x, max_x);
+                let dz = z - z.clamp(min_z, max_z);
+                dx * dx + dz * dz <= radius * radius
+            }
+            TrimShape::Rectangle {
+                min_x: rect_min_x,
+                min_z: rect_min_z,
+                max_x: rect_max_x,
+                max_z: rect_max_z,
+            } => {
+                chunk_x * 16 <= *rect_max_x
+                    && chunk_x * 16 + 15 >= *rect_min_x
+                    && chunk_z * 16 <= *rect_max_z
+                    && chunk_z * 16 + 15 >= *rect_min_z
+            }
+            TrimShape::Polygon(rings) => {
+                let center = (min_x + 7.5, min_z + 7.5);
+                if polygon_contains(rings, center) {
+                    return true;
+                }
+
+                let corners = [
+                    (min_x, min_z),
+                    (max_x, min_z),
+                    (max_x, max_z),
+                    (min_x, max_z),
+                ];
+
+                rings.iter().any(|ring| {
+                    ring.iter().enumerate().any(|(i, &a)| {
+                        let b = ring[(i + 1) % ring.len()];
+
+                        let vertex_inside =
+                            a.0 >= min_x && a.0 <= max_x && a.1 >= min_z && a.1 <= max_z;
+
+                        vertex_inside
+                            || (0..4)
+                                .any(|j| segments_intersect(a, b, corners[j], corners[(j + 1) % 4]))
+                    })
+                })
+            }
+        }
+    }
+}
+
+fn collect_geojson_rings(
+    value: &JsonValue,
+    rings: &mut Vec<Vec<(f64, f64)>>,
+) -> Result<(), String> {
+    let kind = value.get("type").and_then(|t| t.as_str()).unwrap_or("");
+
+    match kind {
+        "FeatureCollection" => {
+            for feature in value
+                .get("features")
+                .and_then(|f| f.as_array())
+                .ok_or("'features' not found or not an array")?
+            {
+                collect_geojson_rings(feature, rings)?;
+            }
+        }
+        "Feature" => {
+            let geometry = value.get("geometry").ok_or("'geometry' not found")?;
+            collect_geojson_rings(geometry, rings)?;
+        }
+        "Polygon" => {
+            let coordinates = value.get("coordinates").ok_or("'coordinates' not found")?;
+            rings.extend(parse_geojson_polygon(coordinates)?);
+        }
+        "MultiPolygon" => {
+            for polygon in value
+                .get("coordinates")
+                .and_then(|c| c.as_array())
+                .ok_or("'coordinates' not found or not an array")?
+            {
+                rings.extend(parse_geojson_polygon(polygon)?);
+            }
+        }
+        v => return Err(format!("Unsupported GeoJSON type '{v}'")),
+    }
+
+    Ok(())
+}
+
+fn parse_geojson_polygon(coordinates: &JsonValue) -> Result<Vec<Vec<(f64, f64)>>, String> {
+    let rings = coordinates
+        .as_array()
+        .ok_or("Polygon coordinates are not an array")?;
+
+    rings
+        .iter()
+        .map(|ring| {
+            ring.as_array()
+                .ok_or("Polygon ring is not an array")?
+                .iter()
+                .map(|point| match point.as_array().map(|p| p.as_slice()) {
+                    Some([x, z, ..]) => match (x.as_f64(), z.as_f64()) {
+                        (Some(x), Some(z)) => Ok((x, z)),
+                        _ => Err(format!("Invalid point {point}")),
+                    },
+                    _ => Err(format!("Invalid point {point}")),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Even-odd rule over all the rings
+fn polygon_contains(rings: &[Vec<(f64, f64)>], (x, z): (f64, f64)) -> bool {
+    let mut inside = false;
+
+    for ring in rings {
+        for (i, &(ax, az)) in ring.iter().enumerate() {
+            let (bx, bz) = ring[(i + ring.len() - 1) % ring.len()];
+
+            if (az > z) != (bz > z) && x < (bx - ax) * (z - az) / (bz - az) + ax {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+fn segments_intersect(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> bool {
+    let cross = |o: (f64, f64), p: (f64, f64), q: (f64, f64)| {
+        (p.0 - o.0) * (q.1 - o.1) - (p.1 - o.1) * (q.0 - o.0)
+    };
+
+    let d1 = cross(c, d, a);
+    let d2 = cross(c, d, b);
+    let d3 = cross(a, b, c);
+    let d4 = cross(a, b, d);
+
+    (d1 > 0.0) != (d2 > 0.0) && (d3 > 0.0) != (d4 > 0.0)
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TrimSummary {
+    pub chunks_kept: usize,
+    pub chunks_removed: usize,
+    pub regions_deleted: usize,
+    pub regions_rewritten: usize,
+    pub bytes_removed: u64,
+}
+
+/// Remove the chunks of the region outside of the shape. Returns the number of chunks removed
+pub fn trim_region_file(
+    region_file: &mut RegionFile,
+    region_x: i32,
+    region_z: i32,
+    shape: &TrimShape,
+) -> usize {
+    let mut removed = 0;
+
+    for local_z in 0..32 {
+        for local_x in 0..32 {
+            let chunk_x = region_x * 32 + local_x;
+            let chunk_z = region_z * 32 + local_z;
+
+            if shape.contains_chunk(chunk_x, chunk_z) {
+                continue;
+            }
+
+            if region_file.remove_chunk(local_x, local_z).is_some() {
+                removed += 1;
+            }
+        }
+    }
+
+    removed
+}
+
+/// Remove every chunk of the world outside of the shape, in its region folder and in the
+/// `entities` and `poi` folders next to it, so no entity or point of interest is left in the
+/// removed chunks. Region files left empty are deleted, the others are rewritten. The files
+/// are only modified once every folder is trimmed. With `dry_run`, only the summaries are
+/// computed. Returns the summary of each folder
+pub fn trim_world(
+    world: &World,
+    shape: &TrimShape,
+    dry_run: bool,
+) -> io::Result<Vec<(PathBuf, TrimSummary)>> {
+    if !dry_run && world.archive.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Worlds in archives are read-only",
+        ));
+    }
+
+    let mut summaries = Vec::new();
+    let mut staged = Vec::new();
+    let mut deleted = Vec::new();
+
+    let siblings = world.sibling_folders()?;
+    for world in std::iter::once(world).chain(&siblings) {
+        let summary = trim_folder(world, shape, dry_run, &mut staged, &mut deleted)?;
+        summaries.push((world.folder.clone(), summary));
+    }
+
+    for file in staged {
+        file.commit()?;
+    }
+    for path in deleted {
+        remove_file(path)?;
+    }
+
+    Ok(summaries)
+}
+
+/// Trim the region files of a folder, staging the rewritten files and listing the files to
+/// delete
+fn trim_folder(
+    world: &World,
+    shape: &TrimShape,
+    dry_run: bool,
+    staged: &mut Vec<StagedFile>,
+    deleted: &mut Vec<PathBuf>,
+) -> io::Result<TrimSummary> {
+    let mut summary = TrimSummary::default();
+
+    for info in &world.regions {
+        let data = world.read_region_bytes(info)?;
+        let old_size = data.len() as u64;
+        let mut region_file = RegionFile::from_bytes(&data)?;
+
+        let removed = trim_region_file(&mut region_file, info.x, info.z, shape);
+        summary.chunks_removed += removed;
+        summary.chunks_kept += region_file.chunks.iter().filter(|c| c.is_some()).count();
+
+        if removed == 0 {
+            continue;
+        }
+
+        if region_file.is_empty() {
+            summary.regions_deleted += 1;
+            summary.bytes_removed += old_size;
+
+            if !dry_run {
+                deleted.push(info.path.clone());
+            }
+        } else {
+            let format = RegionFormat::from_path(&info.path).unwrap_or(RegionFormat::Anvil);
+            let bytes = format.encode(&region_file)?;
+            summary.regions_rewritten += 1;
+            summary.bytes_removed += old_size.saturating_sub(bytes.len() as u64);
+
+            if !dry_run {
+                staged.push(StagedFile::write(&info.path, &bytes)?);
+            }
+        }
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,348 @@
+use crate::{
+    archives::{is_archive_path, Archive},
+    chunks::Chunk,
+    regions::{Region, RegionFile, RegionFormat},
+};
+use std::{
+    fs::{read, read_dir, remove_file, rename, write},
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// A region file found in a world folder
+#[derive(Debug, Clone)]
+pub struct RegionFileInfo {
+    pub x: i32,
+    pub z: i32,
+    /// Path on the file system, or inside the archive of the world
+    pub path: PathBuf,
+}
+
+/// A folder containing region files, e.g. `world/region` or `world/DIM-1/region`
+#[derive(Debug, Clone)]
+pub struct World {
+    pub folder: PathBuf,
+    /// Non-empty region files, sorted by X then Z
+    pub regions: Vec<RegionFileInfo>,
+    /// Archive containing the folder. Worlds in archives are read-only
+    pub archive: Option<Arc<Archive>>,
+}
+
+impl World {
+    /// Open a region folder, or an archive (`.zip`, `.tar`, `.tar.gz`) containing one.
+    /// The folder inside the archive can be given after a `#`, e.g. `backup.zip#world/DIM-1/region`,
+    /// otherwise the overworld region folder is used
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<World> {
+        let path = path.as_ref();
+
+        if !path.is_dir() {
+            let (archive_path, folder) = match path.to_str().and_then(|p| p.rsplit_once('#')) {
+                Some((archive_path, folder)) => (Path::new(archive_path), Some(folder)),
+                None => (path, None),
+            };
+
+            if archive_path.is_file() && is_archive_path(archive_path) {
+                return World::from_archive(Archive::open(archive_path)?, folder);
+            }
+        }
+
+        World::open_folder(path)
+    }
+
+    /// Use a region folder of an archive, or its overworld region folder if not set
+    pub fn from_archive(archive: Archive, folder: Option<&str>) -> io::Result<World> {
+        let folder = match folder {
+            Some(folder) => folder.trim_matches('/').to_string(),
+            None => archive.default_region_folder().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "No region files in the archive")
+            })?,
+        };
+
+        let mut regions = Vec::new();
+
+        for (path, size) in archive.files() {
+            let (file_folder, name) = path.rsplit_once('/').unwrap_or(("", path));
+            if file_folder != folder || size == 0 {
+                continue;
+            }
+
+            if let Some((x, z)) = parse_region_file_name(name) {
+                regions.push(RegionFileInfo {
+                    x,
+                    z,
+                    path: PathBuf::from(path),
+                });
+            }
+        }
+
+        sort_regions(&mut regions);
+
+        Ok(World {
+            folder: PathBuf::from(folder),
+            regions,
+            archive: Some(Arc::new(archive)),
+        })
+    }
+
+    pub fn open_folder<P: AsRef<Path>>(folder: P) -> io::Result<World> {
+        let folder = folder.as_ref().to_path_buf();
+        let mut regions = Vec::new();
+
+        for entry in read_dir(&folder)? {
+            let path = entry?.path();
+
+            let Some((x, z)) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(parse_region_file_name)
+            else {
+                continue;
+            };
+
+            // Minecraft sometimes leaves empty region files behind
+            if path.metadata().map(|meta| meta.len() == 0).unwrap_or(true) {
+                continue;
+            }
+
+            regions.push(RegionFileInfo { x, z, path });
+        }
+
+        sort_regions(&mut regions);
+
+        Ok(World {
+            folder,
+            regions,
+            archive: None,
+        })
+    }
+
+    /// The `entities` and `poi` folders next to a `region` folder, which store other data of
+    /// the same chunks. Only the existing folders are returned, and none for other folders
+    /// and for worlds in archives
+    pub fn sibling_folders(&self) -> io::Result<Vec<World>> {
+        if self.archive.is_some() || self.folder.file_name() != Some("region".as_ref()) {
+            return Ok(Vec::new());
+        }
+
+        let Some(parent) = self.folder.parent() else {
+            return Ok(Vec::new());
+        };
+
+        ["entities", "poi"]
+            .iter()
+            .map(|name| parent.join(name))
+            .filter(|folder| folder.is_dir())
+            .map(World::open_folder)
+            .collect()
+    }
+
+    pub fn region(&self, x: i32, z: i32) -> Option<&RegionFileInfo> {
+        self.regions.iter().find(|r| r.x == x && r.z == z)
+    }
+
+    /// Path of the region file at these region coordinates, even if it does not exist
+    pub fn region_path(&self, x: i32, z: i32) -> PathBuf {
+        self.folder.join(format!("r.{x}.{z}.mca"))
+    }
+
+    /// Read and parse the region at these region coordinates, if it exists
+    pub fn read_region(&self, x: i32, z: i32) -> io::Result<Option<Region>> {
+        match self.region(x, z) {
+            Some(info) => self.read_region_file(info).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Chunks of the neighbouring regions within `width` chunks of the border of a region,
+    /// e.g. to blend or shade the edges of its image. Only these chunks are parsed
+    pub fn read_border_chunks(&self, x: i32, z: i32, width: i32) -> io::Result<Vec<Chunk>> {
+        let width = width.clamp(0, 32);
+        let mut chunks = Vec::new();
+
+        for (dx, dz) in NEIGHBOR_OFFSETS {
+            let Some(info) = self.region(x + dx, z + dz) else {
+                continue;
+            };
+            let region_file = self.read_region_file_raw(info)?;
+
+            for local_z in facing_range(dz, width) {
+                for local_x in facing_range(dx, width) {
+                    if let Some(chunk) = region_file.get_chunk(local_x, local_z)? {
+                        chunks.push(chunk);
+                    }
+                }
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    pub fn read_region_bytes(&self, info: &RegionFileInfo) -> io::Result<Vec<u8>> {
+        match &self.archive {
+            Some(archive) => archive.read(&info.path.to_string_lossy()),
+            None => read(&info.path),
+        }
+    }
+
+    pub fn read_region_file(&self, info: &RegionFileInfo) -> io::Result<Region> {
+        self.read_region_file_raw(info)?.to_region()
+    }
+
+    /// Read a region file of the world without parsing its chunks
+    pub fn read_region_file_raw(&self, info: &RegionFileInfo) -> io::Result<RegionFile> {
+        RegionFile::from_bytes(&self.read_region_bytes(info)?)
+    }
+
+    pub fn write_region_file(
+        &self,
+        info: &RegionFileInfo,
+        region_file: &RegionFile,
+    ) -> io::Result<()> {
+        self.stage_region_file(info, region_file)?.commit()
+    }
+
+    /// Write a region file next to the one of the world, to replace it once every region of
+    /// an edit is written
+    pub fn stage_region_file(
+        &self,
+        info: &RegionFileInfo,
+        region_file: &RegionFile,
+    ) -> io::Result<StagedFile> {
+        if self.archive.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Worlds in archives are read-only",
+            ));
+        }
+
+        // Edited chunks use the modern layout, which McRegion files cannot hold
+        if RegionFormat::from_path(&info.path) == Some(RegionFormat::McRegion) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "McRegion files are read-only",
+            ));
+        }
+
+        stage_region_file(&info.path, region_file)
+    }
+}
+
+/// Offsets of the 8 regions around a region
+pub(crate) const NEIGHBOR_OFFSETS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Local columns or rows of a neighbour at offset `d` touching the region, along one axis
+pub(crate) fn facing_range(d: i32, width: i32) -> Range<i32> {
+    match d {
+        -1 => 32 - width..32,
+        0 => 0..32,
+        _ => 0..width,
+    }
+}
+
+/// Sort the regions by X then Z. Worlds upgraded to anvil keep their McRegion files, which are
+/// only used where there is no other region file
+fn sort_regions(regions: &mut Vec<RegionFileInfo>) {
+    regions.sort_by_key(|r| {
+        let legacy = RegionFormat::from_path(&r.path) == Some(RegionFormat::McRegion);
+        (r.x, r.z, legacy)
+    });
+    regions.dedup_by_key(|r| (r.x, r.z));
+}
+
+/// Parse the region coordinates from a file name like `r.-1.2.mca`, `r.-1.2.linear` or `r.-1.2.mcr`
+pub fn parse_region_file_name(name: &str) -> Option<(i32, i32)> {
+    let mut parts = name.split('.');
+
+    if parts.next()? != "r" {
+        return None;
+    }
+
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+
+    match (parts.next(), parts.next()) {
+        (Some("mca" | "linear" | "mcr"), None) => Some((x, z)),
+        _ => None,
+    }
+}
+
+pub fn read_region_file(path: &Path) -> io::Result<Region> {
+    read_region_file_raw(path)?.to_region()
+}
+
+/// Read a region file without parsing its chunks
+pub fn read_region_file_raw(path: &Path) -> io::Result<RegionFile> {
+    RegionFile::from_bytes(&read(path)?)
+}
+
+/// Write a region file in the format of its extension, anvil by default
+pub fn write_region_file(path: &Path, region_file: &RegionFile) -> io::Result<()> {
+    stage_region_file(path, region_file)?.commit()
+}
+
+/// Write a region file next to `path`, to replace it later
+pub fn stage_region_file(path: &Path, region_file: &RegionFile) -> io::Result<StagedFile> {
+    let format = RegionFormat::from_path(path).unwrap_or(RegionFormat::Anvil);
+    StagedFile::write(path, &format.encode(region_file)?)
+}
+
+/// Replace a file without leaving it truncated if the write fails midway
+pub fn write_file_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    StagedFile::write(path, bytes)?.commit()
+}
+
+/// New content of a file, written to a temporary file in the same folder. Committing renames
+/// it over the target, so the target is either unchanged or fully written. The temporary file
+/// is removed if the staged file is dropped without being committed
+#[derive(Debug)]
+pub struct StagedFile {
+    temp_path: Option<PathBuf>,
+    path: PathBuf,
+}
+
+impl StagedFile {
+    pub fn write(path: &Path, bytes: &[u8]) -> io::Result<StagedFile> {
+        let file_name = path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "The path has no file name")
+        })?;
+
+        let mut temp_name = file_name.to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        let staged = StagedFile {
+            temp_path: Some(temp_path.clone()),
+            path: path.to_path_buf(),
+        };
+        write(&temp_path, bytes)?;
+
+        Ok(staged)
+    }
+
+    /// Replace the target with the staged content
+    pub fn commit(mut self) -> io::Result<()> {
+        match self.temp_path.take() {
+            Some(temp_path) => rename(temp_path, &self.path),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for StagedFile {
+    fn drop(&mut self) {
+        if let Some(temp_path) = self.temp_path.take() {
+            let _ = remove_file(temp_path);
+        }
+    }
+}
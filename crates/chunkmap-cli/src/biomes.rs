@@ -0,0 +1,70 @@
+use anvilregion::{
+    blocks::BoundingBox,
+    dimensions::Dimension,
+    editing::{edit_world_area, replace_biomes},
+    worlds::{write_file_atomic, World},
+};
+use std::{error::Error, fs::create_dir_all, path::Path, time::SystemTime};
+
+/// Add the `minecraft:` namespace if there is none
+pub fn namespaced(name: &str) -> String {
+    if name.contains(':') {
+        name.to_string()
+    } else {
+        format!("minecraft:{name}")
+    }
+}
+
+/// Open the world to modify. With an output folder, the region files are copied there first,
+/// which also allows editing a copy of a world stored in an archive
+pub fn open_world_for_edit(
+    input_path: &str,
+    output_path: Option<&str>,
+) -> Result<World, Box<dyn Error>> {
+    let world = World::open(input_path)?;
+
+    let Some(output_path) = output_path else {
+        return Ok(world);
+    };
+
+    create_dir_all(output_path)?;
+
+    for info in &world.regions {
+        if let Some(name) = info.path.file_name() {
+            write_file_atomic(
+                &Path::new(output_path).join(name),
+                &world.read_region_bytes(info)?,
+            )?;
+        }
+    }
+
+    Ok(World::open(output_path)?)
+}
+
+pub fn now_timestamp() -> u32 {
+    SystemTime::UNIX_EPOCH.elapsed().unwrap().as_secs() as u32
+}
+
+pub fn set_biomes(
+    input_path: &str,
+    output_path: Option<&str>,
+    dimension: &Dimension,
+    area: &BoundingBox,
+    biome: &str,
+    from: Option<&str>,
+) -> Result<usize, Box<dyn Error>> {
+    let world = open_world_for_edit(input_path, output_path)?;
+
+    let biome = namespaced(biome);
+    let from = from.map(namespaced);
+
+    let mut cells = 0;
+    let chunks = edit_world_area(&world, dimension, area, now_timestamp(), |editor| {
+        cells += replace_biomes(editor, area, from.as_deref(), &biome)?;
+        Ok(())
+    })?;
+
+    eprintln!("Changed {cells} biome cells");
+
+    Ok(chunks)
+}
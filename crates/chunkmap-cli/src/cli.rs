@@ -1,4 +1,13 @@
-use clap::{command, Parser};
+use anvilregion::{
+    blocks::{BlockPosition, BoundingBox},
+    dimensions::{get_dimension_heights, Dimension},
+};
+use chunkmap::{
+    hillshade::Hillshade,
+    modes::{RenderMode, RenderModes},
+};
+use clap::{builder::PossibleValuesParser, Parser};
+use std::sync::{Arc, OnceLock};
 
 #[derive(Parser, Debug)]
 #[command(name = "chunkmap")]
@@ -28,17 +37,352 @@ pub enum Commands {
         /// Dimension to render
         #[arg(short, long, value_parser = clap::builder::PossibleValuesParser::new(["overworld", "nether", "end"]))]
         d: String,
+        /// Render modes, separated by commas. Several modes are rendered in a single pass, each in a folder of the output directory
+        #[arg(short, long, required_unless_present = "compare", value_delimiter = ',', value_parser = render_mode_values())]
+        r: Vec<String>,
+        /// Draw the outlines and names of the structures
+        #[arg(short, long)]
+        s: bool,
+        /// Older snapshot of the region folder. The changes since it are drawn over the greyed out terrain, in a `changes` folder of the output directory if there are render modes
+        #[arg(long)]
+        compare: Option<String>,
+        /// Block colors to layer over the defaults, as a JSON object of namespaced ids and `#rrggbb` colors. Can be repeated
+        #[arg(long)]
+        palette: Vec<String>,
+        /// Biomes to layer over the defaults, as a JSON list with the format of `biomes.json`. Can be repeated
+        #[arg(long)]
+        biome_palette: Vec<String>,
+        /// Radius in blocks of the grass, foliage and water color blending, like the biome blend option of the game
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=7))]
+        blend: u8,
+        /// Shade the slopes lit by the sun, as `azimuth,altitude,strength` in degrees and from 0 to 1. Works with every render mode
+        #[arg(long, num_args = 0..=1, default_missing_value = "315,45,0.5")]
+        hillshade: Option<String>,
+        /// Resource packs of the `blocktextures` mode: folders, `.zip` files or the client `.jar`. The last one has the highest priority
+        #[arg(long, required_if_eq("r", "blocktextures"))]
+        textures: Vec<String>,
+        /// Save 512x512 tiles named `t.x.z.png` instead of region images
+        #[arg(long)]
+        tiles: bool,
+    },
+    /// Find the positions of blocks in a world
+    Find {
+        /// Input folder containing chunk data
+        folder: String,
+        /// Block to find, with optional properties (e.g. `spawner` or `furnace[lit=true]`). Can be repeated
+        #[arg(short, long, required = true)]
+        b: Vec<String>,
+        /// Output format
+        #[arg(short, long, default_value = "json", value_parser = clap::builder::PossibleValuesParser::new(["json", "csv"]))]
+        f: String,
+        /// Output file. Prints to stdout if not set
+        #[arg(short, long)]
+        o: Option<String>,
+    },
+    /// Count the blocks and biomes of a world
+    Stats {
+        /// Input folder containing chunk data
+        folder: String,
+        /// Aggregate the counts per chunk, per region or for the whole world
+        #[arg(short, long, default_value = "world", value_parser = clap::builder::PossibleValuesParser::new(["chunk", "region", "world"]))]
+        p: String,
+        /// Also count by Y level
+        #[arg(short, long)]
+        y: bool,
+        /// Output format
+        #[arg(short, long, default_value = "json", value_parser = clap::builder::PossibleValuesParser::new(["json", "csv"]))]
+        f: String,
+        /// Output file. Prints to stdout if not set
+        #[arg(short, long)]
+        o: Option<String>,
+    },
+    /// Set or replace the biomes of an area
+    Biome {
+        /// Input folder containing chunk data
+        folder: String,
+        /// Biome to set (e.g. `plains` or `minecraft:ocean`)
+        #[arg(short, long)]
+        b: String,
+        /// Only replace this biome
+        #[arg(short, long)]
+        r: Option<String>,
+        /// Block area, as `x1,z1,x2,z2` for the whole height or `x1,y1,z1,x2,y2,z2`
+        #[arg(short, long, conflicts_with = "c", required_unless_present = "c")]
+        a: Option<String>,
+        /// Chunk area, as `x1,z1,x2,z2`
+        #[arg(short, long)]
+        c: Option<String>,
+        /// Dimension of the world
+        #[arg(short, long, value_parser = clap::builder::PossibleValuesParser::new(["overworld", "nether", "end"]))]
+        d: String,
+        /// Output folder. The region files are modified in place if not set
+        #[arg(short, long)]
+        o: Option<String>,
+    },
+    /// Remove the chunks outside of a circle, a rectangle or a polygon
+    #[command(group(clap::ArgGroup::new("shape").required(true).args(["c", "r", "g"])))]
+    Trim {
+        /// Input folders containing region files. The `entities` and `poi` folders next to a
+        /// `region` folder are trimmed with it
+        #[arg(required = true)]
+        folders: Vec<String>,
+        /// Circle to keep, as `x,z,radius` in blocks
+        #[arg(short, long)]
+        c: Option<String>,
+        /// Rectangle to keep, as `x1,z1,x2,z2` in blocks
+        #[arg(short, long)]
+        r: Option<String>,
+        /// GeoJSON file containing the polygons to keep, in block coordinates
+        #[arg(short, long)]
+        g: Option<String>,
+        /// Only print what would be removed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Export an area of the world as a schematic
+    ExportSchematic {
+        /// Input folder containing chunk data
+        folder: String,
+        /// Block area, as `x1,y1,z1,x2,y2,z2` or `x1,z1,x2,z2` for the whole height
+        #[arg(short, long)]
+        a: String,
+        /// Dimension of the world
+        #[arg(short, long, value_parser = clap::builder::PossibleValuesParser::new(["overworld", "nether", "end"]))]
+        d: String,
+        /// Output file. The format depends on the extension: `.schem` (Sponge), `.nbt` (structure) or `.litematic`
+        #[arg(short, long)]
+        o: String,
+    },
+    /// Paste a schematic (`.schem` or structure `.nbt`) into the world
+    Paste {
+        /// Input folder containing chunk data
+        folder: String,
+        /// Schematic file
+        #[arg(short, long)]
+        s: String,
+        /// Position of the lower corner of the schematic, as `x,y,z`
+        #[arg(short, long)]
+        p: String,
+        /// Clockwise rotation in degrees
+        #[arg(short, long, default_value = "0", value_parser = clap::builder::PossibleValuesParser::new(["0", "90", "180", "270"]))]
+        r: String,
+        /// Mirror applied before the rotation: `leftright` flips north and south, `frontback` flips east and west
+        #[arg(short, long, default_value = "none", value_parser = clap::builder::PossibleValuesParser::new(["none", "leftright", "frontback"]))]
+        m: String,
+        /// Keep the existing blocks where the schematic has air
+        #[arg(long)]
+        skip_air: bool,
+        /// Dimension of the world
+        #[arg(short, long, value_parser = clap::builder::PossibleValuesParser::new(["overworld", "nether", "end"]))]
+        d: String,
+        /// Output folder. The region files are modified in place if not set
+        #[arg(short, long)]
+        o: Option<String>,
+    },
+    /// Compare two snapshots of a world chunk by chunk, as JSON
+    Diff {
+        /// Folder containing the old region files
+        old: String,
+        /// Folder containing the new region files
+        new: String,
+        /// Output file. Prints to stdout if not set
+        #[arg(short, long)]
+        o: Option<String>,
+    },
+    /// Render the same area of several snapshots of a world as an animation
+    Timelapse {
+        /// Region folders of the snapshots, from the oldest to the newest
+        #[arg(required = true)]
+        folders: Vec<String>,
+        /// Block area, as `x1,z1,x2,z2`
+        #[arg(short, long)]
+        a: String,
+        /// Dimension to render
+        #[arg(short, long, value_parser = clap::builder::PossibleValuesParser::new(["overworld", "nether", "end"]))]
+        d: String,
         /// Render mode
-        #[arg(short, long, value_parser = clap::builder::PossibleValuesParser::new([
-            "textures",
-            "texturesnowater",
-            "heightmap",
-            "biomes",
-            "temperature",
-            "downfall",
-            "inhabited",
-            "lastupdated"
-        ]))]
+        #[arg(short, long, default_value = "textures", value_parser = timelapse_mode_values())]
         r: String,
+        /// Output file: `.png` for an animated PNG, `.gif`, or a folder for numbered PNG frames
+        #[arg(short, long)]
+        o: String,
+        /// Delay between frames, in milliseconds
+        #[arg(long, default_value_t = 500)]
+        delay: u16,
+        /// Caption of each frame: the date of the newest region file, or the folder name
+        #[arg(short, long, value_parser = clap::builder::PossibleValuesParser::new(["date", "name"]))]
+        c: Option<String>,
+        /// Block colors to layer over the defaults, as a JSON object of namespaced ids and `#rrggbb` colors. Can be repeated
+        #[arg(long)]
+        palette: Vec<String>,
+        /// Biomes to layer over the defaults, as a JSON list with the format of `biomes.json`. Can be repeated
+        #[arg(long)]
+        biome_palette: Vec<String>,
+        /// Radius in blocks of the grass, foliage and water color blending, like the biome blend option of the game
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=7))]
+        blend: u8,
+        /// Shade the slopes lit by the sun, as `azimuth,altitude,strength` in degrees and from 0 to 1. Works with every render mode
+        #[arg(long, num_args = 0..=1, default_missing_value = "315,45,0.5")]
+        hillshade: Option<String>,
+    },
+    /// Convert region files between the anvil (`.mca`) and linear (`.linear`) formats
+    Convert {
+        /// Input folder containing region files
+        folder: String,
+        /// Format to convert to
+        #[arg(short, long, value_parser = clap::builder::PossibleValuesParser::new(["mca", "linear"]))]
+        f: String,
+        /// Output folder
+        #[arg(short, long)]
+        o: String,
     },
+    /// Generate a block color palette from resource packs, to be used with `--palette`
+    Colors {
+        /// Resource pack folders, `.zip` files or the client `.jar`. The last one has the
+        /// highest priority
+        #[arg(required = true)]
+        packs: Vec<String>,
+        /// Output JSON file, printed if not set
+        #[arg(short, long)]
+        o: Option<String>,
+    },
+}
+
+pub fn parse_dimension(d: &str) -> Dimension {
+    match d {
+        "overworld" => Dimension::Overworld,
+        "nether" => Dimension::Nether,
+        "end" => Dimension::End,
+        _ => panic!("Invalid dimension. Allowed: overworld | nether | end"),
+    }
+}
+
+static RENDER_MODES: OnceLock<RenderModes> = OnceLock::new();
+
+/// Set the render modes of the CLI, before parsing the arguments
+pub fn set_render_modes(modes: RenderModes) {
+    if RENDER_MODES.set(modes).is_err() {
+        panic!("The render modes are already set");
+    }
+}
+
+/// Render modes of the CLI, given to `run`. The built-in modes if not set
+pub fn render_modes() -> &'static RenderModes {
+    RENDER_MODES.get_or_init(RenderModes::default)
+}
+
+/// Values of `-r` for `render`
+fn render_mode_values() -> PossibleValuesParser {
+    PossibleValuesParser::new(render_modes().iter().map(|mode| mode.name()))
+}
+
+/// Values of `-r` for `timelapse`, whose frames have one pixel per block
+fn timelapse_mode_values() -> PossibleValuesParser {
+    PossibleValuesParser::new(
+        render_modes()
+            .iter()
+            .filter(|mode| mode.pixels_per_block() == 1)
+            .map(|mode| mode.name()),
+    )
+}
+
+pub fn parse_render_mode(r: &str) -> Arc<dyn RenderMode> {
+    match render_modes().get(r) {
+        Some(mode) => mode,
+        None => panic!(
+            "Invalid render mode. Allowed: {}",
+            render_modes().names().join(" | ")
+        ),
+    }
+}
+
+/// Parse `azimuth,altitude,strength`
+pub fn parse_hillshade(hillshade: &str) -> Result<Hillshade, String> {
+    let values = hillshade
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid hillshade '{hillshade}': {e}"))?;
+
+    match values[..] {
+        [azimuth, altitude, strength] if (0.0..=90.0).contains(&altitude) => Ok(Hillshade {
+            azimuth,
+            altitude,
+            strength: strength.clamp(0.0, 1.0),
+        }),
+        _ => Err(format!(
+            "Invalid hillshade '{hillshade}'. Expected: azimuth,altitude,strength with an altitude from 0 to 90"
+        )),
+    }
+}
+
+/// Parse `x1,z1,x2,z2` (whole height of the dimension) or `x1,y1,z1,x2,y2,z2`
+pub fn parse_area(area: &str, dimension: &Dimension) -> Result<BoundingBox, String> {
+    let values = area
+        .split(',')
+        .map(|v| v.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid area '{area}': {e}"))?;
+
+    let (min_y, max_y) = get_dimension_heights(dimension);
+
+    let (a, b) = match values[..] {
+        [x1, z1, x2, z2] => (
+            BlockPosition {
+                x: x1,
+                y: min_y,
+                z: z1,
+            },
+            BlockPosition {
+                x: x2,
+                y: max_y - 1,
+                z: z2,
+            },
+        ),
+        [x1, y1, z1, x2, y2, z2] => (
+            BlockPosition {
+                x: x1,
+                y: y1,
+                z: z1,
+            },
+            BlockPosition {
+                x: x2,
+                y: y2,
+                z: z2,
+            },
+        ),
+        _ => return Err(format!("Invalid area '{area}': expected 4 or 6 values")),
+    };
+
+    Ok(BoundingBox::from_corners(&a, &b))
+}
+
+/// Parse a block position `x,y,z`
+pub fn parse_position(position: &str) -> Result<BlockPosition, String> {
+    let values = position
+        .split(',')
+        .map(|v| v.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid position '{position}': {e}"))?;
+
+    match values[..] {
+        [x, y, z] => Ok(BlockPosition { x, y, z }),
+        _ => Err(format!("Invalid position '{position}': expected 3 values")),
+    }
+}
+
+/// Parse chunk coordinates `x1,z1,x2,z2` into the block area they cover
+pub fn parse_chunk_area(area: &str, dimension: &Dimension) -> Result<BoundingBox, String> {
+    if area.split(',').count() != 4 {
+        return Err(format!("Invalid chunk area '{area}': expected 4 values"));
+    }
+
+    let mut bb = parse_area(area, dimension)?;
+
+    bb.min_x *= 16;
+    bb.min_z *= 16;
+    bb.max_x = bb.max_x * 16 + 15;
+    bb.max_z = bb.max_z * 16 + 15;
+
+    Ok(bb)
 }
@@ -0,0 +1,36 @@
+use anvilregion::{
+    regions::RegionFormat,
+    worlds::{write_file_atomic, World},
+};
+use std::{error::Error, fs::create_dir_all, path::Path};
+
+/// Convert every region file of the world to the format, in the output folder.
+/// Returns the number of region files written
+pub fn convert_folder(
+    input_path: &str,
+    output_path: &str,
+    format: RegionFormat,
+) -> Result<usize, Box<dyn Error>> {
+    let world = World::open(input_path)?;
+    create_dir_all(output_path)?;
+
+    for info in &world.regions {
+        if RegionFormat::from_path(&info.path) == Some(RegionFormat::McRegion) {
+            return Err(format!("Cannot convert the McRegion file {:?}", info.path).into());
+        }
+
+        let mut region_file = world.read_region_file_raw(info)?;
+
+        if format == RegionFormat::Anvil {
+            region_file.compress_chunks()?;
+        }
+
+        let name = format!("r.{}.{}.{}", info.x, info.z, format.extension());
+        write_file_atomic(
+            &Path::new(output_path).join(name),
+            &format.encode(&region_file)?,
+        )?;
+    }
+
+    Ok(world.regions.len())
+}
@@ -0,0 +1,20 @@
+use crate::output::create_output;
+use anvilregion::{
+    diff::{diff_worlds, WorldDiff},
+    worlds::World,
+};
+use std::{error::Error, io::Write};
+
+pub fn diff_folders(
+    old_path: &str,
+    new_path: &str,
+    output_path: Option<&str>,
+) -> Result<WorldDiff, Box<dyn Error>> {
+    let diff = diff_worlds(&World::open(old_path)?, &World::open(new_path)?)?;
+
+    let mut output = create_output(output_path)?;
+    serde_json::to_writer_pretty(&mut output, &diff)?;
+    writeln!(output)?;
+
+    Ok(diff)
+}
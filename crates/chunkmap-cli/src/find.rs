@@ -0,0 +1,68 @@
+use crate::output::{create_output, csv_field};
+use anvilregion::{
+    search::{search_block_queries, BlockQuery},
+    worlds::World,
+};
+use std::{error::Error, io::Write};
+
+pub fn find_blocks(
+    input_path: &str,
+    queries: &[String],
+    format: &str,
+    output_path: Option<&str>,
+) -> Result<usize, Box<dyn Error>> {
+    let queries = queries
+        .iter()
+        .map(|q| q.parse::<BlockQuery>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let world = World::open(input_path)?;
+    let mut out = create_output(output_path)?;
+    let mut count = 0;
+
+    // Each match is written as soon as it is found, so large searches do not fill the memory
+    if format == "csv" {
+        writeln!(out, "x,y,z,chunk_x,chunk_z,block")?;
+    } else {
+        write!(out, "[")?;
+    }
+
+    for result in search_block_queries(&world, queries) {
+        let m = match result {
+            Ok(m) => m,
+            Err(e) => {
+                eprintln!("Failed to search region: {e}");
+                continue;
+            }
+        };
+
+        if format == "csv" {
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                m.position.x,
+                m.position.y,
+                m.position.z,
+                m.chunk.x,
+                m.chunk.z,
+                csv_field(&m.block.to_string())
+            )?;
+        } else {
+            // Same layout as a pretty-printed list
+            let separator = if count == 0 { "" } else { "," };
+            let json = serde_json::to_string_pretty(&m)?.replace('\n', "\n  ");
+            write!(out, "{separator}\n  {json}")?;
+        }
+
+        count += 1;
+    }
+
+    if format != "csv" {
+        let end = if count == 0 { "]" } else { "\n]" };
+        writeln!(out, "{end}")?;
+    }
+
+    out.flush()?;
+
+    Ok(count)
+}
@@ -0,0 +1,304 @@
+use crate::{
+    biomes::set_biomes,
+    cli::{
+        parse_area, parse_chunk_area, parse_dimension, parse_hillshade, parse_position,
+        parse_render_mode, set_render_modes, Cli, Commands,
+    },
+    convert::convert_folder,
+    diff::diff_folders,
+    find::find_blocks,
+    render::{create_renderer, render_regions},
+    schematic::{export_area, parse_transform, paste_schematic},
+    stats::compute_stats,
+    timelapse::render_timelapse,
+    trim::trim_folders,
+};
+use anvilregion::regions::RegionFormat;
+use chunkmap::{
+    images::create_map_image,
+    modes::RenderModes,
+    renderer::RenderOptions,
+    resource_packs::{colors_to_json, ResourcePacks},
+};
+use clap::Parser;
+use std::fs::write;
+
+mod biomes;
+mod cli;
+mod convert;
+mod diff;
+mod find;
+mod output;
+mod render;
+mod schematic;
+mod stats;
+mod timelapse;
+mod trim;
+
+/// Run the CLI with the arguments of the process. `modes` are the render modes available to
+/// `-r`, so another crate can build the CLI with its own modes:
+///
+/// ```no_run
+/// use chunkmap::modes::RenderModes;
+///
+/// let mut modes = RenderModes::default();
+/// // modes.register(my_crate::SlimeChunks);
+/// chunkmap_cli::run(modes);
+/// ```
+pub fn run(modes: RenderModes) {
+    set_render_modes(modes);
+    let args = Cli::parse();
+
+    match args.command {
+        Commands::Merge { folder, o } => {
+            if !o.ends_with(".png") {
+                panic!("Output must be a PNG file");
+            }
+
+            let image = match create_map_image(&folder) {
+                Ok(v) => v,
+                Err(e) => {
+                    panic!("Failed to merge regions: {e:?}");
+                }
+            };
+
+            image.save(&o).expect("Failed to save final image");
+
+            println!("Merged regions in {o}");
+        }
+        Commands::Render {
+            folder,
+            o,
+            d,
+            r,
+            s,
+            compare,
+            palette,
+            biome_palette,
+            textures,
+            tiles,
+            blend,
+            hillshade,
+        } => {
+            let dimension = parse_dimension(&d);
+            let hillshade =
+                hillshade.map(|h| parse_hillshade(&h).unwrap_or_else(|e| panic!("{e}")));
+
+            let modes: Vec<_> = r.iter().map(|r| parse_render_mode(r)).collect();
+
+            let mut options = RenderOptions {
+                dimension,
+                blend_radius: blend,
+                hillshade,
+                ..RenderOptions::default()
+            };
+            if let Some(mode) = modes.first() {
+                options.render_mode = mode.clone();
+            }
+            let renderer = match create_renderer(options, &palette, &biome_palette, &textures) {
+                Ok(v) => v,
+                Err(e) => {
+                    panic!("Failed to load the palettes: {e:?}");
+                }
+            };
+
+            render_regions(&folder, &o, renderer, &modes, s, compare, tiles);
+
+            println!("All regions rendered");
+        }
+        Commands::Find { folder, b, f, o } => {
+            let count = match find_blocks(&folder, &b, &f, o.as_deref()) {
+                Ok(v) => v,
+                Err(e) => {
+                    panic!("Failed to find blocks: {e:?}");
+                }
+            };
+
+            eprintln!("Found {count} blocks");
+        }
+        Commands::Stats { folder, p, y, f, o } => {
+            if let Err(e) = compute_stats(&folder, &p, y, &f, o.as_deref()) {
+                panic!("Failed to compute stats: {e:?}");
+            }
+
+            eprintln!("Stats computed");
+        }
+        Commands::Biome {
+            folder,
+            b,
+            r,
+            a,
+            c,
+            d,
+            o,
+        } => {
+            let dimension = parse_dimension(&d);
+
+            let area = match (a, c) {
+                (Some(a), _) => parse_area(&a, &dimension),
+                (None, Some(c)) => parse_chunk_area(&c, &dimension),
+                (None, None) => panic!("An area or a chunk area is required"),
+            }
+            .unwrap_or_else(|e| panic!("{e}"));
+
+            let count = match set_biomes(&folder, o.as_deref(), &dimension, &area, &b, r.as_deref())
+            {
+                Ok(v) => v,
+                Err(e) => {
+                    panic!("Failed to set biomes: {e:?}");
+                }
+            };
+
+            println!("Modified {count} chunks");
+        }
+        Commands::Trim {
+            folders,
+            c,
+            r,
+            g,
+            dry_run,
+        } => {
+            if let Err(e) = trim_folders(&folders, c, r, g, dry_run) {
+                panic!("Failed to trim the world: {e:?}");
+            }
+        }
+        Commands::ExportSchematic { folder, a, d, o } => {
+            let dimension = parse_dimension(&d);
+            let area = parse_area(&a, &dimension).unwrap_or_else(|e| panic!("{e}"));
+
+            let count = match export_area(&folder, &area, &o) {
+                Ok(v) => v,
+                Err(e) => {
+                    panic!("Failed to export the schematic: {e:?}");
+                }
+            };
+
+            println!("Exported {count} blocks to {o}");
+        }
+        Commands::Paste {
+            folder,
+            s,
+            p,
+            r,
+            m,
+            skip_air,
+            d,
+            o,
+        } => {
+            let dimension = parse_dimension(&d);
+            let origin = parse_position(&p).unwrap_or_else(|e| panic!("{e}"));
+            let transform = parse_transform(&r, &m);
+
+            let count = match paste_schematic(
+                &folder,
+                o.as_deref(),
+                &dimension,
+                &s,
+                &origin,
+                &transform,
+                skip_air,
+            ) {
+                Ok(v) => v,
+                Err(e) => {
+                    panic!("Failed to paste the schematic: {e:?}");
+                }
+            };
+
+            println!("Modified {count} chunks");
+        }
+        Commands::Diff { old, new, o } => {
+            let diff = match diff_folders(&old, &new, o.as_deref()) {
+                Ok(v) => v,
+                Err(e) => {
+                    panic!("Failed to compare the worlds: {e:?}");
+                }
+            };
+
+            eprintln!(
+                "{} added, {} removed and {} modified chunks, {} changed blocks",
+                diff.added_chunks, diff.removed_chunks, diff.modified_chunks, diff.changed_blocks
+            );
+        }
+        Commands::Timelapse {
+            folders,
+            a,
+            d,
+            r,
+            o,
+            delay,
+            c,
+            palette,
+            biome_palette,
+            blend,
+            hillshade,
+        } => {
+            let dimension = parse_dimension(&d);
+            let area = parse_area(&a, &dimension).unwrap_or_else(|e| panic!("{e}"));
+            let hillshade =
+                hillshade.map(|h| parse_hillshade(&h).unwrap_or_else(|e| panic!("{e}")));
+
+            let options = RenderOptions {
+                dimension,
+                render_mode: parse_render_mode(&r),
+                blend_radius: blend,
+                hillshade,
+            };
+            let renderer = match create_renderer(options, &palette, &biome_palette, &[]) {
+                Ok(v) => v,
+                Err(e) => {
+                    panic!("Failed to load the palettes: {e:?}");
+                }
+            };
+
+            let count = match render_timelapse(&folders, &area, &renderer, &o, delay, c.as_deref())
+            {
+                Ok(v) => v,
+                Err(e) => {
+                    panic!("Failed to render the timelapse: {e:?}");
+                }
+            };
+
+            println!("Rendered {count} frames to {o}");
+        }
+        Commands::Convert { folder, f, o } => {
+            let format = match f.as_str() {
+                "mca" => RegionFormat::Anvil,
+                "linear" => RegionFormat::Linear,
+                _ => panic!("Invalid format. Allowed: mca | linear"),
+            };
+
+            let count = match convert_folder(&folder, &o, format) {
+                Ok(v) => v,
+                Err(e) => {
+                    panic!("Failed to convert the region files: {e:?}");
+                }
+            };
+
+            println!("Converted {count} region files to {o}");
+        }
+        Commands::Colors { packs, o } => {
+            let resource_packs = match ResourcePacks::open(&packs) {
+                Ok(v) => v,
+                Err(e) => panic!("Failed to open the resource packs: {e:?}"),
+            };
+
+            let json = match resource_packs
+                .block_colors()
+                .and_then(|colors| colors_to_json(&colors))
+            {
+                Ok(v) => v,
+                Err(e) => panic!("Failed to compute the block colors: {e:?}"),
+            };
+
+            match o {
+                Some(path) => {
+                    if let Err(e) = write(&path, json) {
+                        panic!("Failed to write the palette: {e:?}");
+                    }
+                    println!("Palette saved to {path}");
+                }
+                None => println!("{json}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+/// Write to the output file, or to stdout if there is none
+pub fn create_output(path: Option<&str>) -> io::Result<Box<dyn Write>> {
+    match path {
+        Some(path) => Ok(Box::new(io::BufWriter::new(File::create(path)?))),
+        None => Ok(Box::new(io::BufWriter::new(io::stdout()))),
+    }
+}
+
+/// Quote a CSV field if needed
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
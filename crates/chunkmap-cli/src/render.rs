@@ -1,49 +1,150 @@
 use anvilregion::{
-    dimensions::Dimension,
-    regions::{parse_region_bytes, Region},
+    borders::BorderChunkCache, regions::Region, structures::read_overlapping_starts, worlds::World,
+};
+use chunkmap::{
+    images::{split_tiles, RegionImages},
+    modes::RenderMode,
+    overlays::draw_structure_overlay,
+    renderer::{RenderOptions, Renderer},
+    resource_packs::ResourcePacks,
 };
-use chunkmap::images::{create_region_images, ImageRenderType};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::{
-    collections::VecDeque,
-    fs::{create_dir_all, read_dir, File},
-    io::{self, Read},
-    path::Path,
+    collections::{BTreeSet, VecDeque},
+    error::Error,
+    fs::{create_dir_all, read_to_string},
     sync::{Arc, Mutex},
     thread,
     time::Duration,
 };
 
-fn parse_region_file(path: &Path) -> io::Result<Region> {
-    let mut file = File::open(path)?;
-    let mut data = Vec::new();
-    file.read_to_end(&mut data)?;
-    parse_region_bytes(&data)
+/// Create the renderer, with the palette files layered over the default colors in order,
+/// and the block textures of the resource packs if any
+pub fn create_renderer(
+    options: RenderOptions,
+    palettes: &[String],
+    biome_palettes: &[String],
+    resource_packs: &[String],
+) -> Result<Renderer, Box<dyn Error>> {
+    let mut renderer = Renderer::new(options)?;
+
+    for path in palettes {
+        renderer
+            .add_block_palette(&read_to_string(path)?)
+            .map_err(|e| format!("Invalid palette '{path}': {e}"))?;
+    }
+
+    for path in biome_palettes {
+        renderer
+            .add_biome_palette(&read_to_string(path)?)
+            .map_err(|e| format!("Invalid biome palette '{path}': {e}"))?;
+    }
+
+    if !resource_packs.is_empty() {
+        renderer.set_resource_packs(&ResourcePacks::open(resource_packs)?)?;
+    }
+
+    Ok(renderer)
 }
 
+/// Render the changes since the same region file in the compare world.
+/// A missing region file is considered empty
+fn render_changes(
+    renderer: &Renderer,
+    region: &Region,
+    (x, z): (i32, i32),
+    compare_world: &World,
+) -> Result<RegionImages, Box<dyn Error>> {
+    let old_chunks = match compare_world.read_region(x, z)? {
+        Some(old_region) => old_region.chunks,
+        None => Vec::new(),
+    };
+
+    renderer.render_changes(&old_chunks, &region.chunks)
+}
+
+/// Render a region in every mode, parsing its chunks once, then its changes if there is a
+/// compare world. The chunks around the region come from the border cache
+fn render_region(
+    renderer: &Renderer,
+    world: &World,
+    borders: Option<&BorderChunkCache>,
+    region: &Region,
+    (x, z): (i32, i32),
+    modes: &[Arc<dyn RenderMode>],
+    compare_world: Option<&World>,
+) -> Result<Vec<RegionImages>, Box<dyn Error>> {
+    let modes: Vec<&dyn RenderMode> = modes.iter().map(|mode| mode.as_ref()).collect();
+
+    let mut images = if let Some(borders) = borders {
+        borders.add_region(x, z, &region.chunks);
+        let neighbors = borders.border_chunks(world, x, z)?;
+        renderer.render_chunks_in_modes(&region.chunks, &neighbors, &modes)?
+    } else {
+        renderer.render_chunks_in_modes(&region.chunks, &[], &modes)?
+    };
+
+    if let Some(compare_world) = compare_world {
+        images.push(render_changes(renderer, region, (x, z), compare_world)?);
+    }
+
+    Ok(images)
+}
+
+/// Render the regions in every mode, and their changes since the compare world if any. With
+/// several images per region, the images of each mode are saved in a folder named after it,
+/// and the changes in a `changes` folder
 pub fn render_regions(
     input_path: &str,
     output_path: &str,
-    render_type: ImageRenderType,
-    dimension: Dimension,
+    renderer: Renderer,
+    modes: &[Arc<dyn RenderMode>],
+    draw_structures: bool,
+    compare_path: Option<String>,
+    tiles: bool,
 ) {
-    let region_path = Path::new(input_path);
-
-    let mca_files: Vec<_> = read_dir(region_path)
-        .expect("Failed to read region folder")
-        .filter_map(|entry| entry.ok())
-        .map(|entry| entry.path())
-        .filter(|path| {
-            path.extension().map(|ext| ext == "mca").unwrap_or(false)
-                && path.metadata().map(|meta| meta.len() > 0).unwrap_or(false)
-        })
-        .collect();
-    let file_count = mca_files.len();
-
-    let file_queue = Arc::new(Mutex::new(VecDeque::from(mca_files)));
+    // Folders or archives
+    let world = Arc::new(World::open(input_path).expect("Failed to read region folder"));
+    let compare_world = compare_path
+        .map(|path| Arc::new(World::open(path).expect("Failed to read compare folder")));
+
+    // The regions of both snapshots, so the regions removed since the compare world are drawn
+    let mut positions: BTreeSet<(i32, i32)> = world.regions.iter().map(|r| (r.x, r.z)).collect();
+    if let Some(compare_world) = &compare_world {
+        positions.extend(compare_world.regions.iter().map(|r| (r.x, r.z)));
+    }
+
+    let file_count = positions.len();
+
+    // Shared by all the threads, so the color tables are only loaded once
+    let renderer = Arc::new(renderer);
+
+    // Each region is read once for the borders of its neighbours
+    let borders = modes
+        .iter()
+        .any(|mode| renderer.uses_neighbors(mode.as_ref()))
+        .then(|| Arc::new(BorderChunkCache::new(&world, renderer.neighbors_width())));
+
+    let file_queue = Arc::new(Mutex::new(VecDeque::from_iter(positions)));
     let mut handles = Vec::new();
 
-    create_dir_all(output_path).unwrap();
+    let mut names: Vec<&str> = modes.iter().map(|mode| mode.name()).collect();
+    if compare_world.is_some() {
+        names.push("changes");
+    }
+
+    let folders: Vec<String> = match names[..] {
+        [_] => vec![output_path.to_string()],
+        _ => names
+            .iter()
+            .map(|name| format!("{output_path}/{name}"))
+            .collect(),
+    };
+    for folder in &folders {
+        create_dir_all(folder).unwrap();
+    }
+    let folders = Arc::new(folders);
+    let modes = Arc::new(modes.to_vec());
 
     let num_threads = thread::available_parallelism().unwrap().get();
 
@@ -71,32 +172,75 @@ pub fn render_regions(
         let status_bar = Arc::clone(&status_bar);
         let file_queue = Arc::clone(&file_queue);
 
-        let output_path = output_path.to_string().clone();
-        let dimension = dimension.clone();
-        let render_type = render_type.clone();
+        let folders = Arc::clone(&folders);
+        let modes = Arc::clone(&modes);
+        let renderer = Arc::clone(&renderer);
+        let world = Arc::clone(&world);
+        let borders = borders.clone();
+        let compare_world = compare_world.clone();
 
         let handle = thread::spawn(move || loop {
-            let path_opt = {
+            let position_opt = {
                 let mut queue = file_queue.lock().unwrap();
                 queue.pop_front()
             };
 
-            let path = match path_opt {
+            let (x, z) = match position_opt {
                 Some(p) => p,
                 None => break,
             };
 
             status_bar.set_prefix(format!("{}/{}", thread_idx, num_threads));
-            status_bar.set_message(format!("{:?}", path.file_name().unwrap()));
+            status_bar.set_message(format!("r.{x}.{z}"));
+
+            // Regions only in the compare world have no chunks left
+            let region = match world.region(x, z) {
+                Some(info) => world.read_region_file(info),
+                None => Ok(Region { chunks: Vec::new() }),
+            };
 
-            match parse_region_file(&path) {
+            match region {
                 Ok(region) => {
-                    match create_region_images(&region.chunks, &dimension, &render_type) {
-                        Ok(imgs) => {
-                            for (rx, rz, img) in imgs {
-                                let filename =
-                                    format!("{}/r.{}.{}.png", output_path.clone(), rx, rz);
-                                img.save(&filename).unwrap();
+                    let images = render_region(
+                        &renderer,
+                        &world,
+                        borders.as_deref(),
+                        &region,
+                        (x, z),
+                        &modes,
+                        compare_world.as_deref(),
+                    );
+
+                    match images {
+                        Ok(images) => {
+                            let starts = if draw_structures {
+                                // With the structures crossing the border of the region
+                                read_overlapping_starts(&world, &region.chunks).unwrap_or_else(
+                                    |e| {
+                                        eprintln!("Failed to parse structures: {}", e);
+                                        Vec::new()
+                                    },
+                                )
+                            } else {
+                                Vec::new()
+                            };
+
+                            for (imgs, output_path) in images.into_iter().zip(folders.iter()) {
+                                for (rx, rz, mut img) in imgs {
+                                    draw_structure_overlay(&mut img, rx, rz, &starts);
+
+                                    if tiles {
+                                        for (tx, tz, tile) in split_tiles(&img, rx, rz) {
+                                            let filename =
+                                                format!("{}/t.{}.{}.png", output_path, tx, tz);
+                                            tile.save(&filename).unwrap();
+                                        }
+                                    } else {
+                                        let filename =
+                                            format!("{}/r.{}.{}.png", output_path, rx, rz);
+                                        img.save(&filename).unwrap();
+                                    }
+                                }
                             }
                         }
                         Err(e) => {
@@ -0,0 +1,91 @@
+use crate::biomes::{now_timestamp, open_world_for_edit};
+use anvilregion::{
+    blocks::{BlockPosition, BoundingBox},
+    dimensions::Dimension,
+    schematics::{export_schematic, extract_volume, paste_volume, read_schematic, SchematicFormat},
+    transforms::{Mirror, Rotation, Transform},
+    worlds::World,
+};
+use std::{
+    error::Error,
+    fs::{read, write},
+    path::Path,
+    time::SystemTime,
+};
+
+pub fn export_area(
+    input_path: &str,
+    area: &BoundingBox,
+    output_path: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let format = SchematicFormat::from_path(output_path)
+        .ok_or("Unknown schematic format. Allowed extensions: .schem | .nbt | .litematic")?;
+
+    let name = Path::new(output_path)
+        .file_stem()
+        .and_then(|v| v.to_str())
+        .unwrap_or("schematic");
+
+    let world = World::open(input_path)?;
+    let volume = extract_volume(&world, area)?;
+
+    let timestamp = SystemTime::UNIX_EPOCH.elapsed()?.as_millis() as i64;
+    write(
+        output_path,
+        export_schematic(&volume, format, name, timestamp)?,
+    )?;
+
+    let blocks = volume
+        .blocks
+        .iter()
+        .filter(|&&i| !volume.palette[i].is_air())
+        .count();
+
+    Ok(blocks)
+}
+
+pub fn paste_schematic(
+    input_path: &str,
+    output_path: Option<&str>,
+    dimension: &Dimension,
+    schematic_path: &str,
+    origin: &BlockPosition,
+    transform: &Transform,
+    skip_air: bool,
+) -> Result<usize, Box<dyn Error>> {
+    let volume = read_schematic(&read(schematic_path)?)?.transformed(transform);
+    let world = open_world_for_edit(input_path, output_path)?;
+
+    eprintln!(
+        "Pasting {}x{}x{} blocks",
+        volume.size_x, volume.size_y, volume.size_z
+    );
+
+    paste_volume(
+        &world,
+        dimension,
+        &volume,
+        origin,
+        skip_air,
+        now_timestamp(),
+    )
+}
+
+pub fn parse_transform(rotation: &str, mirror: &str) -> Transform {
+    let rotation = match rotation {
+        "0" => Rotation::None,
+        "90" => Rotation::Clockwise90,
+        "180" => Rotation::Clockwise180,
+        "270" => Rotation::CounterClockwise90,
+        _ => panic!("Invalid rotation. Allowed: 0 | 90 | 180 | 270"),
+    };
+
+    let mirror = match mirror {
+        "none" => Mirror::None,
+        "leftright" => Mirror::LeftRight,
+        "frontback" => Mirror::FrontBack,
+        _ => panic!("Invalid mirror. Allowed: none | leftright | frontback"),
+    };
+
+    Transform::new(mirror, rotation)
+}
@@ -0,0 +1,139 @@
+use crate::output::{create_output, csv_field};
+use anvilregion::{
+    stats::{chunk_stats, BlockStats},
+    worlds::World,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::{collections::BTreeMap, error::Error, io::Write};
+
+#[derive(Serialize)]
+struct LocatedStats {
+    x: i32,
+    z: i32,
+    stats: BlockStats,
+}
+
+pub fn compute_stats(
+    input_path: &str,
+    per: &str,
+    by_y: bool,
+    format: &str,
+    output_path: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let world = World::open(input_path)?;
+
+    let bar = ProgressBar::new(world.regions.len() as u64);
+    bar.set_style(ProgressStyle::with_template("[{bar:40.cyan/blue}] {pos}/{len}").unwrap());
+
+    let mut world_total = BlockStats::default();
+    let mut located = Vec::new();
+
+    for info in &world.regions {
+        let region = match world.read_region_file(info) {
+            Ok(v) => v,
+            Err(e) => {
+                bar.println(format!("Failed to parse region {}.{}: {e}", info.x, info.z));
+                bar.inc(1);
+                continue;
+            }
+        };
+
+        // Malformed chunks are reported and skipped, like the regions
+        let mut region_total = BlockStats::default();
+
+        for chunk in &region.chunks {
+            let stats = match chunk_stats(chunk, by_y) {
+                Ok(v) => v,
+                Err(e) => {
+                    bar.println(format!(
+                        "Failed to count chunk {}.{}: {e}",
+                        chunk.position.x, chunk.position.z
+                    ));
+                    continue;
+                }
+            };
+
+            match per {
+                "chunk" => located.push(LocatedStats {
+                    x: chunk.position.x,
+                    z: chunk.position.z,
+                    stats,
+                }),
+                _ => region_total.merge(&stats),
+            }
+        }
+
+        match per {
+            "chunk" => {}
+            "region" => located.push(LocatedStats {
+                x: info.x,
+                z: info.z,
+                stats: region_total,
+            }),
+            _ => world_total.merge(&region_total),
+        }
+
+        bar.inc(1);
+    }
+
+    bar.finish_and_clear();
+
+    let mut out = create_output(output_path)?;
+
+    if format == "csv" {
+        writeln!(out, "x,z,type,name,y,count")?;
+
+        if per == "world" {
+            write_csv_rows(&mut out, "", &world_total)?;
+        } else {
+            for l in &located {
+                write_csv_rows(&mut out, &format!("{},{}", l.x, l.z), &l.stats)?;
+            }
+        }
+    } else {
+        if per == "world" {
+            serde_json::to_writer_pretty(&mut out, &world_total)?;
+        } else {
+            serde_json::to_writer_pretty(&mut out, &located)?;
+        }
+        writeln!(out)?;
+    }
+
+    out.flush()?;
+
+    Ok(())
+}
+
+fn write_csv_rows(
+    out: &mut dyn Write,
+    location: &str,
+    stats: &BlockStats,
+) -> Result<(), Box<dyn Error>> {
+    // Rows without a Y are the totals
+    let location = if location.is_empty() { "," } else { location };
+
+    write_csv_group(out, location, "block", &stats.blocks, &stats.blocks_by_y)?;
+    write_csv_group(out, location, "biome", &stats.biomes, &stats.biomes_by_y)?;
+
+    Ok(())
+}
+
+fn write_csv_group(
+    out: &mut dyn Write,
+    location: &str,
+    kind: &str,
+    totals: &BTreeMap<String, u64>,
+    by_y: &BTreeMap<i32, BTreeMap<String, u64>>,
+) -> Result<(), Box<dyn Error>> {
+    for (name, count) in totals {
+        writeln!(out, "{location},{kind},{},,{count}", csv_field(name))?;
+    }
+    for (y, counts) in by_y {
+        for (name, count) in counts {
+            writeln!(out, "{location},{kind},{},{y},{count}", csv_field(name))?;
+        }
+    }
+
+    Ok(())
+}
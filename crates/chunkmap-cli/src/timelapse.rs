@@ -0,0 +1,115 @@
+use anvilregion::{archives::ARCHIVE_EXTENSIONS, blocks::BoundingBox, worlds::World};
+use chunkmap::{
+    renderer::Renderer,
+    timelapse::{draw_caption, encode_apng, encode_gif},
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{
+    error::Error,
+    fs::{create_dir_all, write},
+    path::Path,
+    time::SystemTime,
+};
+
+/// Format days since the epoch as `YYYY-MM-DD`
+fn format_date(days: i64) -> String {
+    // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Caption of a snapshot: the date its newest region file (or its archive) was saved, or the
+/// name of the folder (the parent folder for `region` folders)
+fn snapshot_caption(folder: &str, world: &World, caption: &str) -> String {
+    if caption == "date" {
+        let paths: Vec<&Path> = match &world.archive {
+            Some(archive) => archive.path.iter().map(|p| p.as_path()).collect(),
+            None => world
+                .regions
+                .iter()
+                .map(|info| info.path.as_path())
+                .collect(),
+        };
+
+        let newest = paths
+            .iter()
+            .filter_map(|path| path.metadata().and_then(|m| m.modified()).ok())
+            .max()
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok());
+
+        return match newest {
+            Some(duration) => format_date(duration.as_secs() as i64 / 86_400),
+            None => String::new(),
+        };
+    }
+
+    // Archive name without the folder inside it
+    let path = Path::new(folder.split('#').next().unwrap_or(folder));
+    let path = match path.file_name() {
+        Some(name) if name == "region" => path.parent().unwrap_or(path),
+        _ => path,
+    };
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    ARCHIVE_EXTENSIONS
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+pub fn render_timelapse(
+    folders: &[String],
+    area: &BoundingBox,
+    renderer: &Renderer,
+    output_path: &str,
+    delay_ms: u16,
+    caption: Option<&str>,
+) -> Result<usize, Box<dyn Error>> {
+    let bar = ProgressBar::new(folders.len() as u64);
+    bar.set_style(ProgressStyle::with_template("[{bar:40.cyan/blue}] {pos}/{len}").unwrap());
+
+    let mut frames = Vec::with_capacity(folders.len());
+
+    for folder in folders {
+        let world = World::open(folder)?;
+        let mut frame =
+            renderer.render_area(&world, (area.min_x, area.min_z), (area.max_x, area.max_z))?;
+
+        if let Some(caption) = caption {
+            draw_caption(&mut frame, &snapshot_caption(folder, &world, caption));
+        }
+
+        frames.push(frame);
+        bar.inc(1);
+    }
+
+    bar.finish_and_clear();
+
+    let lower = output_path.to_lowercase();
+    if lower.ends_with(".png") {
+        write(output_path, encode_apng(&frames, delay_ms)?)?;
+    } else if lower.ends_with(".gif") {
+        write(output_path, encode_gif(&frames, delay_ms)?)?;
+    } else {
+        create_dir_all(output_path)?;
+        for (i, frame) in frames.iter().enumerate() {
+            frame.save(Path::new(output_path).join(format!("frame_{:04}.png", i + 1)))?;
+        }
+    }
+
+    Ok(frames.len())
+}
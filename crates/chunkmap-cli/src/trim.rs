@@ -0,0 +1,74 @@
+use anvilregion::{
+    trim::{trim_world, TrimShape},
+    worlds::World,
+};
+use std::{error::Error, fmt::Display, fs::read_to_string, str::FromStr};
+
+fn parse_values<T: FromStr>(s: &str, count: usize) -> Result<Vec<T>, String>
+where
+    T::Err: Display,
+{
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<T>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid value in '{s}': {e}"))?;
+
+    if values.len() != count {
+        return Err(format!("Expected {count} values in '{s}'"));
+    }
+
+    Ok(values)
+}
+
+pub fn trim_folders(
+    folders: &[String],
+    circle: Option<String>,
+    rectangle: Option<String>,
+    geojson: Option<String>,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let shape = if let Some(c) = circle {
+        let v = parse_values::<f64>(&c, 3)?;
+        TrimShape::Circle {
+            x: v[0],
+            z: v[1],
+            radius: v[2],
+        }
+    } else if let Some(r) = rectangle {
+        // Inclusive block bounds
+        let v = parse_values::<i32>(&r, 4)?;
+        TrimShape::Rectangle {
+            min_x: v[0].min(v[2]),
+            min_z: v[1].min(v[3]),
+            max_x: v[0].max(v[2]),
+            max_z: v[1].max(v[3]),
+        }
+    } else if let Some(g) = geojson {
+        TrimShape::from_geojson(&read_to_string(g)?)?
+    } else {
+        return Err("A circle, a rectangle or a GeoJSON file is required".into());
+    };
+
+    for folder in folders {
+        let world = World::open(folder)?;
+
+        for (folder, summary) in trim_world(&world, &shape, dry_run)? {
+            println!(
+                "{}: {} chunks removed, {} kept, {} region files deleted, {} rewritten, {:.2} MiB removed",
+                folder.display(),
+                summary.chunks_removed,
+                summary.chunks_kept,
+                summary.regions_deleted,
+                summary.regions_rewritten,
+                summary.bytes_removed as f64 / (1024.0 * 1024.0)
+            );
+        }
+    }
+
+    if dry_run {
+        println!("Dry run, nothing was modified");
+    }
+
+    Ok(())
+}
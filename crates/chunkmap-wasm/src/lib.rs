@@ -1,9 +1,12 @@
-use anvilregion::{dimensions::Dimension, regions::parse_region_bytes};
-use chunkmap::images::{create_region_images, ImageRenderType};
-use console_error_panic_hook;
+use anvilregion::{
+    archives::Archive,
+    regions::{parse_region_bytes, Region},
+    worlds::World,
+};
+use chunkmap::renderer::{RenderOptions, Renderer};
 use image::ImageFormat;
 use serde::Serialize;
-use std::io::Cursor;
+use std::{io::Cursor, sync::Mutex};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -18,6 +21,41 @@ pub fn initialize() {
     log("[WASM] Loaded");
 }
 
+/// Created on first use, then reused by every render
+static RENDERER: Mutex<Option<Renderer>> = Mutex::new(None);
+
+fn with_renderer<T>(f: impl FnOnce(&mut Renderer) -> T) -> Result<T, JsError> {
+    let mut renderer = RENDERER
+        .lock()
+        .map_err(|_| JsError::new("Renderer poisoned"))?;
+
+    if renderer.is_none() {
+        let created = Renderer::new(RenderOptions::default())
+            .map_err(|e| JsError::new(&format!("Failed to load the color tables: {e}")))?;
+        *renderer = Some(created);
+    }
+
+    Ok(f(renderer.as_mut().unwrap()))
+}
+
+/// Layer block colors over the current ones, from a JSON object of namespaced ids and
+/// `#rrggbb` colors. Returns the number of colors
+#[wasm_bindgen]
+pub fn add_block_palette(bytes: &[u8]) -> Result<usize, JsError> {
+    let json = std::str::from_utf8(bytes)?;
+    with_renderer(|renderer| renderer.add_block_palette(json))?
+        .map_err(|e| JsError::new(&format!("Invalid palette: {e}")))
+}
+
+/// Layer biomes over the current ones, from a JSON list with the format of `biomes.json`.
+/// Returns the number of biomes
+#[wasm_bindgen]
+pub fn add_biome_palette(bytes: &[u8]) -> Result<usize, JsError> {
+    let json = std::str::from_utf8(bytes)?;
+    with_renderer(|renderer| renderer.add_biome_palette(json))?
+        .map_err(|e| JsError::new(&format!("Invalid biome palette: {e}")))
+}
+
 #[derive(Serialize)]
 struct JSRegion {
     x: i32,
@@ -25,37 +63,66 @@ struct JSRegion {
     buffer: Vec<u8>,
 }
 
+/// Render a region as PNG images, one per region of the output
+fn render_region(region: &Region) -> Result<Vec<JsValue>, JsError> {
+    match with_renderer(|renderer| renderer.render_chunks(&region.chunks))? {
+        Ok(imgs) => {
+            let mut values = Vec::new();
+
+            for (x, z, img) in imgs {
+                let mut buffer = Vec::new();
+
+                log(&format!("Creating image {x}.{z}"));
+
+                img.write_to(&mut Cursor::new(&mut buffer), ImageFormat::Png)
+                    .unwrap();
+
+                let region = JSRegion { x, z, buffer };
+                values.push(serde_wasm_bindgen::to_value(&region)?);
+            }
+
+            Ok(values)
+        }
+        Err(e) => {
+            let error_str = &format!("Failed to create region images: {e:#?}");
+            log(error_str);
+            Err(JsError::new(error_str))
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub fn parse_region_file(bytes: &[u8]) -> Result<Vec<JsValue>, JsError> {
     match parse_region_bytes(bytes) {
-        Ok(region) => match create_region_images(
-            &region.chunks,
-            &Dimension::Overworld,
-            &ImageRenderType::Textures,
-        ) {
-            Ok(imgs) => {
-                let mut values = Vec::new();
-
-                for (x, z, img) in imgs {
-                    let mut buffer = Vec::new();
+        Ok(region) => render_region(&region),
+        Err(e) => Err(e.into()),
+    }
+}
 
-                    log(&format!("Creating image {x}.{z}"));
+/// Render all the regions of a zipped world. The region folder inside the zip can be set,
+/// e.g. `world/DIM-1/region`, otherwise the overworld is rendered
+#[wasm_bindgen]
+pub fn render_world_archive(
+    bytes: Vec<u8>,
+    folder: Option<String>,
+) -> Result<Vec<JsValue>, JsError> {
+    let archive = Archive::from_zip_bytes(bytes)?;
+    let world = World::from_archive(archive, folder.as_deref())?;
 
-                    img.write_to(&mut Cursor::new(&mut buffer), ImageFormat::Png)
-                        .unwrap();
+    log(&format!(
+        "Found {} region files in {:?}",
+        world.regions.len(),
+        world.folder
+    ));
 
-                    let region = JSRegion { x, z, buffer };
-                    values.push(serde_wasm_bindgen::to_value(&region)?);
-                }
+    let mut values = Vec::new();
 
-                Ok(values)
-            }
-            Err(e) => {
-                let error_str = &format!("Failed to create region images: {e:#?}");
-                log(&error_str);
-                Err(JsError::new(&error_str))
-            }
-        },
-        Err(e) => Err(e.into()),
+    for info in &world.regions {
+        match world.read_region_file(info) {
+            Ok(region) => values.extend(render_region(&region)?),
+            Err(e) => log(&format!("Failed to read {:?}: {e}", info.path)),
+        }
     }
+
+    Ok(values)
 }
@@ -1,7 +1,8 @@
+use crate::blocks::namespaced_id;
 use serde::Deserialize;
 use std::{collections::HashMap, error::Error};
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct BiomeData {
     pub name: String,
     pub temperature: f32,
@@ -11,23 +12,61 @@ pub struct BiomeData {
     pub water_color: u32,
 }
 
+/// A biome of a user palette. The missing values are taken from the biome it replaces,
+/// or from plains for a new biome
+#[derive(Deserialize)]
+pub struct BiomePaletteEntry {
+    pub name: String,
+    pub temperature: Option<f32>,
+    pub downfall: Option<f32>,
+    pub foliage_color: Option<u32>,
+    pub grass_color: Option<u32>,
+    pub water_color: Option<u32>,
+}
+
 // NOTE: Cannot use fs because of WASM support
 const BIOMES_JSON: &str = include_str!("../../../biomes.json");
 
+/// Default biome data, keyed by namespaced biome ids (e.g. `minecraft:plains`)
 pub fn load_biomes_data() -> Result<HashMap<String, BiomeData>, Box<dyn Error>> {
-    let raw_biomes: Vec<BiomeData> = serde_json::from_str(&BIOMES_JSON)?;
+    let raw_biomes: Vec<BiomeData> = serde_json::from_str(BIOMES_JSON)?;
     let mut biomes = HashMap::new();
 
-    for biome in raw_biomes {
+    for mut biome in raw_biomes {
+        biome.name = namespaced_id(&biome.name);
         biomes.insert(biome.name.clone(), biome);
     }
 
     Ok(biomes)
 }
 
-pub fn get_biome_data<'a>(data: &'a HashMap<String, BiomeData>, name: &str) -> &'a BiomeData {
-    data.get(name).unwrap_or_else(|| {
-        eprintln!("No biome data found for '{name}'");
-        data.get("plains").expect("No 'plains' biome found")
-    })
+/// Add or replace biomes from a JSON list with the format of `biomes.json`.
+/// Biomes without a namespace are in the `minecraft` namespace. Returns the number of biomes
+pub fn merge_biomes_data(
+    biomes: &mut HashMap<String, BiomeData>,
+    json: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let entries: Vec<BiomePaletteEntry> = serde_json::from_str(json)?;
+    let count = entries.len();
+
+    for entry in entries {
+        let name = namespaced_id(&entry.name);
+        let base = biomes
+            .get(&name)
+            .or_else(|| biomes.get("minecraft:plains"))
+            .ok_or("No 'minecraft:plains' biome to take the missing values from")?;
+
+        let biome = BiomeData {
+            name: name.clone(),
+            temperature: entry.temperature.unwrap_or(base.temperature),
+            downfall: entry.downfall.unwrap_or(base.downfall),
+            foliage_color: entry.foliage_color.unwrap_or(base.foliage_color),
+            grass_color: entry.grass_color.unwrap_or(base.grass_color),
+            water_color: entry.water_color.unwrap_or(base.water_color),
+        };
+
+        biomes.insert(name, biome);
+    }
+
+    Ok(count)
 }
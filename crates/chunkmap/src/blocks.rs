@@ -1,4 +1,7 @@
-use crate::{biomes::BiomeData, utils::u32_to_rgb};
+use crate::{
+    resource_packs::apply_tint,
+    tints::{block_tint_source, BiomeTints},
+};
 use std::{
     collections::{HashMap, HashSet},
     error::Error,
@@ -9,7 +12,7 @@ pub const UNKNOWN_BLOCK_COLOR: [u8; 3] = [233, 66, 245];
 pub fn get_block_color(
     name: &str,
     snowy: bool,
-    biome_data: &BiomeData,
+    tints: &BiomeTints,
     block_colors: &HashMap<String, [u8; 3]>,
     unknown_blocks: &mut HashSet<String>,
 ) -> [u8; 3] {
@@ -19,35 +22,71 @@ pub fn get_block_color(
 
     let clean_name = name.strip_prefix("minecraft:").unwrap_or(name);
 
+    let color = if name.contains(':') {
+        block_colors.get(name)
+    } else {
+        block_colors.get(&namespaced_id(name))
+    };
+
+    // Blocks tinted in the game: the palette holds the color of their texture, multiplied
+    // by the biome colors like the game does. The fixed tints are already in the palette,
+    // and the tint alone is used for the blocks without a color
+    if let Some(source) = block_tint_source(clean_name) {
+        return match color {
+            Some(&color) if source.is_biome() => apply_tint(color, tints.get(source)),
+            Some(&color) => color,
+            None => tints.get(source),
+        };
+    }
+
     match clean_name {
         "air" | "cave_air" => [0, 0, 0],
-        "grass_block" => u32_to_rgb(biome_data.grass_color),
-        "water" => u32_to_rgb(biome_data.water_color),
         "lava" => [255, 100, 0],
         _ => {
-            if clean_name.contains("leaves") {
-                match clean_name {
-                    "birch_leaves" | "spruce_leaves" | "cherry_leaves" => {}
-                    _ => return u32_to_rgb(biome_data.foliage_color),
-                }
+            // Modded leaves are tinted by the biome, unless they have their own color
+            if clean_name.contains("leaves") && color.is_none() {
+                return tints.foliage;
             }
 
-            if let Some(&color) = block_colors.get(clean_name) {
-                color
-            } else {
-                unknown_blocks.insert(clean_name.to_string());
-                UNKNOWN_BLOCK_COLOR
+            match color {
+                Some(&color) => color,
+                None => {
+                    unknown_blocks.insert(name.to_string());
+                    UNKNOWN_BLOCK_COLOR
+                }
             }
         }
     }
 }
 
+/// Add the `minecraft` namespace to the ids without one
+pub fn namespaced_id(id: &str) -> String {
+    if id.contains(':') {
+        id.to_string()
+    } else {
+        format!("minecraft:{id}")
+    }
+}
+
 // NOTE: Cannot use fs because of WASM support
 pub const BLOCKS_JSON: &str = include_str!("../../../blocks.json");
 
+/// Default block colors, keyed by namespaced block ids (e.g. `minecraft:stone`)
 pub fn load_block_colors() -> Result<HashMap<String, [u8; 3]>, Box<dyn Error>> {
-    let raw_colors: HashMap<String, String> = serde_json::from_str(&BLOCKS_JSON)?;
     let mut block_colors = HashMap::new();
+    merge_block_colors(&mut block_colors, BLOCKS_JSON)?;
+    Ok(block_colors)
+}
+
+/// Add or replace colors from a JSON object of block ids and `#rrggbb` colors, with the
+/// format of `blocks.json`. Ids without a namespace are in the `minecraft` namespace.
+/// Returns the number of colors
+pub fn merge_block_colors(
+    block_colors: &mut HashMap<String, [u8; 3]>,
+    json: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let raw_colors: HashMap<String, String> = serde_json::from_str(json)?;
+    let count = raw_colors.len();
 
     for (block_name, hex_color) in raw_colors {
         let hex = hex_color.trim_start_matches('#');
@@ -55,11 +94,11 @@ pub fn load_block_colors() -> Result<HashMap<String, [u8; 3]>, Box<dyn Error>> {
             let r = u8::from_str_radix(&hex[0..2], 16)?;
             let g = u8::from_str_radix(&hex[2..4], 16)?;
             let b = u8::from_str_radix(&hex[4..6], 16)?;
-            block_colors.insert(block_name, [r, g, b]);
+            block_colors.insert(namespaced_id(&block_name), [r, g, b]);
         } else {
             return Err(format!("Invalid color found in the JSON: {hex_color}").into());
         }
     }
 
-    Ok(block_colors)
+    Ok(count)
 }
@@ -0,0 +1,125 @@
+use crate::{
+    images::RegionImages,
+    modes::Textures,
+    renderer::Renderer,
+    utils::{blend_colors, grey_color},
+};
+use anvilregion::{
+    chunks::Chunk,
+    diff::{for_each_block_change, BlockChange},
+};
+use image::Rgba;
+use std::{collections::HashMap, error::Error};
+
+pub const PLACED_COLOR: [u8; 3] = [60, 220, 60];
+pub const REMOVED_COLOR: [u8; 3] = [230, 40, 40];
+pub const REPLACED_COLOR: [u8; 3] = [250, 190, 20];
+
+/// How much the chunks that only exist in one of the snapshots are tinted
+const CHUNK_TINT: f32 = 0.35;
+
+fn change_color(change: BlockChange) -> [u8; 3] {
+    match change {
+        BlockChange::Placed => PLACED_COLOR,
+        BlockChange::Removed => REMOVED_COLOR,
+        BlockChange::Replaced => REPLACED_COLOR,
+    }
+}
+
+/// Removed blocks are the most important to see, then replaced blocks
+fn change_priority(change: BlockChange) -> u8 {
+    match change {
+        BlockChange::Placed => 0,
+        BlockChange::Replaced => 1,
+        BlockChange::Removed => 2,
+    }
+}
+
+impl Renderer {
+    /// Render the changes between two snapshots of the same chunks. The terrain of the new
+    /// snapshot is greyed out, and every column with a changed block is colored by the kind
+    /// of change: green for placed blocks, red for removed blocks and yellow for replaced ones.
+    /// Chunks that only exist in one snapshot are tinted with the placed or removed color
+    pub fn render_changes(
+        &self,
+        old_chunks: &[Chunk],
+        new_chunks: &[Chunk],
+    ) -> Result<RegionImages, Box<dyn Error>> {
+        let old_by_position: HashMap<(i32, i32), &Chunk> = old_chunks
+            .iter()
+            .map(|c| ((c.position.x, c.position.z), c))
+            .collect();
+        let new_by_position: HashMap<(i32, i32), &Chunk> = new_chunks
+            .iter()
+            .map(|c| ((c.position.x, c.position.z), c))
+            .collect();
+
+        // Removed chunks are drawn from the old snapshot
+        let mut base_chunks = new_chunks.to_vec();
+        base_chunks.extend(
+            old_chunks
+                .iter()
+                .filter(|c| !new_by_position.contains_key(&(c.position.x, c.position.z)))
+                .cloned(),
+        );
+
+        let mut images = self.render_chunks_as(&base_chunks, &[], &Textures)?;
+
+        // Most important change of each column
+        let mut columns: HashMap<(i32, i32), BlockChange> = HashMap::new();
+        // Chunks that only exist in one snapshot
+        let mut chunk_changes: HashMap<(i32, i32), BlockChange> = HashMap::new();
+
+        for chunk in &base_chunks {
+            let position = (chunk.position.x, chunk.position.z);
+            let old = old_by_position.get(&position);
+            let new = new_by_position.get(&position);
+
+            match (old, new) {
+                (Some(old), Some(new)) => {
+                    for_each_block_change(Some(old), Some(new), |p, _, _, change| {
+                        let column = columns.entry((p.x, p.z)).or_insert(change);
+                        if change_priority(change) > change_priority(*column) {
+                            *column = change;
+                        }
+                    })?;
+                }
+                (None, Some(_)) => {
+                    chunk_changes.insert(position, BlockChange::Placed);
+                }
+                _ => {
+                    chunk_changes.insert(position, BlockChange::Removed);
+                }
+            }
+        }
+
+        for (rx, rz, img) in &mut images {
+            let min_x = *rx * 512;
+            let min_z = *rz * 512;
+
+            for (px, pz, pixel) in img.enumerate_pixels_mut() {
+                if pixel[3] == 0 {
+                    continue;
+                }
+
+                let x = min_x + px as i32;
+                let z = min_z + pz as i32;
+                let rgb = [pixel[0], pixel[1], pixel[2]];
+
+                let color = if let Some(change) = columns.get(&(x, z)) {
+                    change_color(*change)
+                } else if let Some(change) =
+                    chunk_changes.get(&(x.div_euclid(16), z.div_euclid(16)))
+                {
+                    blend_colors(grey_color(rgb), change_color(*change), CHUNK_TINT)
+                } else {
+                    grey_color(rgb)
+                };
+
+                *pixel = Rgba([color[0], color[1], color[2], 255]);
+            }
+        }
+
+        Ok(images)
+    }
+}
@@ -0,0 +1,49 @@
+/// Shading of the terrain lit by the sun, from the slope and the aspect of each block column
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hillshade {
+    /// Direction of the sun in degrees, clockwise from the north
+    pub azimuth: f32,
+    /// Height of the sun above the horizon in degrees
+    pub altitude: f32,
+    /// 0 for no shading, 1 for the full hillshade
+    pub strength: f32,
+}
+
+impl Default for Hillshade {
+    fn default() -> Self {
+        Hillshade {
+            azimuth: 315.0,
+            altitude: 45.0,
+            strength: 0.5,
+        }
+    }
+}
+
+impl Hillshade {
+    /// Light factor of a column from the heights around it, north first, 1 for flat terrain.
+    /// The slope and the aspect use the 8 neighbours with the Horn method
+    pub fn factor(&self, heights: &[[f32; 3]; 3]) -> f32 {
+        let [[a, b, c], [d, _, f], [g, h, i]] = *heights;
+
+        // Towards the east and the south, with blocks 1 unit wide
+        let dz_dx = ((c + 2.0 * f + i) - (a + 2.0 * d + g)) / 8.0;
+        let dz_dy = ((g + 2.0 * h + i) - (a + 2.0 * b + c)) / 8.0;
+
+        let slope = (dz_dx * dz_dx + dz_dy * dz_dy).sqrt().atan();
+        let aspect = dz_dy.atan2(-dz_dx);
+
+        let zenith = (90.0 - self.altitude).to_radians();
+        let azimuth = (360.0 - self.azimuth + 90.0).to_radians();
+
+        let light =
+            zenith.cos() * slope.cos() + zenith.sin() * slope.sin() * (azimuth - aspect).cos();
+
+        light.max(0.0) / zenith.cos().max(f32::EPSILON)
+    }
+
+    /// Darken or lighten a color with a light factor
+    pub fn apply(&self, color: [u8; 3], factor: f32) -> [u8; 3] {
+        let factor = 1.0 + self.strength * (factor - 1.0);
+        color.map(|c| (c as f32 * factor).round().clamp(0.0, 255.0) as u8)
+    }
+}
@@ -1,4 +1,13 @@
 pub mod biomes;
 pub mod blocks;
+pub mod changes;
+pub mod fonts;
+pub mod hillshade;
 pub mod images;
+pub mod modes;
+pub mod overlays;
+pub mod renderer;
+pub mod resource_packs;
+pub mod timelapse;
+pub mod tints;
 pub mod utils;
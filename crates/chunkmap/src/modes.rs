@@ -0,0 +1,443 @@
+use crate::{
+    biomes::BiomeData,
+    blocks::{get_block_color, namespaced_id},
+    renderer::Renderer,
+    resource_packs::{apply_tint, BlockTexture},
+    tints::{BiomeTints, TintGrid},
+    utils::{
+        apply_blue_tint, downfall_color, height_color, light_color, linear_color, night_color,
+        spawnability_color, temperature_color, u32_to_rgb,
+    },
+};
+use anvilregion::{blocks::Block, chunks::Chunk};
+use image::Rgba;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{Arc, OnceLock},
+    time::SystemTime,
+};
+
+/// A way of coloring the columns of a map. The built-in modes are registered in
+/// `RenderModes::default`, and other crates can register their own
+pub trait RenderMode: Send + Sync {
+    /// Name of the mode in the frontends, e.g. `textures`
+    fn name(&self) -> &str;
+
+    /// Color of a column
+    fn color(&self, column: &Column) -> [u8; 3];
+
+    /// Width of a block in the images
+    fn pixels_per_block(&self) -> u32 {
+        1
+    }
+
+    /// Colors of the pixels of a column, row by row, for the modes with more than one pixel
+    /// per block. Every pixel has the color of the column by default
+    fn fill_pixels(&self, column: &Column, pixels: &mut [[u8; 3]]) {
+        pixels.fill(self.color(column));
+    }
+
+    /// Whether the colors depend on the blocks around the columns. The chunks around the
+    /// rendered ones are then read, to shade the edges and blend the biome colors
+    fn uses_neighbors(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Debug for dyn RenderMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RenderMode({})", self.name())
+    }
+}
+
+/// A column to color, with its surrounding
+pub struct Column<'a> {
+    /// Highest block of the column, below the water if any
+    pub block: &'a Block,
+    /// Biome of the surface
+    pub biome: &'a BiomeData,
+    /// Chunk of the column, for its metadata
+    pub chunk: &'a Chunk,
+    /// Surface blocks of the region and around it by x and z, if the mode uses the neighbors
+    pub neighbors: &'a HashMap<(i32, i32), &'a Block>,
+    /// Renderer with the color tables, the textures and the options
+    pub renderer: &'a Renderer,
+    pub(crate) tint_grid: Option<&'a TintGrid>,
+    pub(crate) unknown_blocks: &'a RefCell<HashSet<String>>,
+}
+
+impl Column<'_> {
+    /// Grass, foliage and water colors of the column, blended if enabled
+    pub fn tints(&self) -> BiomeTints {
+        let (x, z) = (self.block.position.x, self.block.position.z);
+        self.tint_grid
+            .and_then(|grid| grid.get(x, z))
+            .unwrap_or_else(|| self.renderer.tints.biome_tints(self.biome, x, z))
+    }
+
+    /// Flat color of the block, tinted by the biome. Unknown blocks are reported after the
+    /// render
+    pub fn block_color(&self, tints: &BiomeTints) -> [u8; 3] {
+        get_block_color(
+            &self.block.name,
+            self.block.snowy,
+            tints,
+            &self.renderer.block_colors,
+            &mut self.unknown_blocks.borrow_mut(),
+        )
+    }
+
+    /// Water and 3d effect of the textures modes: black and white shadows from the heights
+    /// of the northern and southern blocks. The hillshade replaces the shadows
+    pub fn shade(&self, mut color: [u8; 3], tints: &BiomeTints, draw_water: bool) -> [u8; 3] {
+        let block = self.block;
+
+        if draw_water && block.depth > 0 {
+            color = apply_blue_tint(color, block.depth, tints.water);
+        }
+
+        if self.renderer.options.hillshade.is_some() || block.depth > 0 {
+            return color;
+        }
+
+        let above_y = self
+            .neighbors
+            .get(&(block.position.x, block.position.z - 1))
+            .map(|b| b.position.y);
+        let below_y = self
+            .neighbors
+            .get(&(block.position.x, block.position.z + 1))
+            .map(|b| b.position.y);
+
+        if let (Some(above), Some(below)) = (above_y, below_y) {
+            if above > block.position.y {
+                let v = (above - block.position.y).min(3);
+                // Black tint
+                for _ in 0..v {
+                    for c in color.iter_mut() {
+                        *c = (*c as f32 * 0.8) as u8;
+                    }
+                }
+            } else if below > block.position.y {
+                let v = (below - block.position.y).min(3);
+                // White tint
+                for _ in 0..v {
+                    for c in color.iter_mut() {
+                        *c = ((*c as f32) * 0.9 + 255.0 * 0.1) as u8;
+                    }
+                }
+            }
+        }
+
+        color
+    }
+
+    /// Shaded color of the block, with or without the water
+    pub fn surface_color(&self, draw_water: bool) -> [u8; 3] {
+        let tints = self.tints();
+        self.shade(self.block_color(&tints), &tints, draw_water)
+    }
+}
+
+/// Blocks with their colors, water and shading
+pub struct Textures;
+
+impl RenderMode for Textures {
+    fn name(&self) -> &str {
+        "textures"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        column.surface_color(true)
+    }
+
+    fn uses_neighbors(&self) -> bool {
+        true
+    }
+}
+
+/// Textures mode showing the blocks under the water
+pub struct TexturesWithoutWater;
+
+impl RenderMode for TexturesWithoutWater {
+    fn name(&self) -> &str {
+        "texturesnowater"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        column.surface_color(false)
+    }
+
+    fn uses_neighbors(&self) -> bool {
+        true
+    }
+}
+
+/// Height of the surface
+pub struct Heightmap;
+
+impl RenderMode for Heightmap {
+    fn name(&self) -> &str {
+        "heightmap"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        height_color(column.block.position.y, &column.renderer.options.dimension)
+    }
+}
+
+/// Grass color of the biomes
+pub struct Biomes;
+
+impl RenderMode for Biomes {
+    fn name(&self) -> &str {
+        "biomes"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        u32_to_rgb(column.biome.grass_color)
+    }
+}
+
+pub struct Temperature;
+
+impl RenderMode for Temperature {
+    fn name(&self) -> &str {
+        "temperature"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        temperature_color(column.biome.temperature)
+    }
+}
+
+pub struct Downfall;
+
+impl RenderMode for Downfall {
+    fn name(&self) -> &str {
+        "downfall"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        downfall_color(column.biome.downfall)
+    }
+}
+
+/// Time players spent in the chunks, up to 1,600,000 ticks
+pub struct Inhabited;
+
+impl RenderMode for Inhabited {
+    fn name(&self) -> &str {
+        "inhabited"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        linear_color(column.chunk.inhabited_time as f32, 0.0, 1_600_000.0)
+    }
+}
+
+/// Last save of the chunks over the past year
+#[derive(Default)]
+pub struct LastUpdated {
+    /// Read on the first render, the clock is not available in WASM
+    now: OnceLock<f32>,
+}
+
+impl RenderMode for LastUpdated {
+    fn name(&self) -> &str {
+        "lastupdated"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        let now = *self.now.get_or_init(|| {
+            SystemTime::UNIX_EPOCH
+                .elapsed()
+                .map_or(0.0, |d| d.as_millis() as f32)
+        });
+
+        linear_color(
+            column.chunk.last_update as f32,
+            now - 365.0 * 24.0 * 60.0 * 60.0 * 1000.0,
+            now,
+        )
+    }
+}
+
+/// Block light above the surface
+pub struct BlockLight;
+
+impl RenderMode for BlockLight {
+    fn name(&self) -> &str {
+        "blocklight"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        light_color(column.block.block_light)
+    }
+}
+
+/// Textures mode highlighting where monsters can spawn
+pub struct Spawnability;
+
+impl RenderMode for Spawnability {
+    fn name(&self) -> &str {
+        "spawnability"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        let color = column.surface_color(true);
+        let block = column.block;
+
+        match block.depth {
+            0 => spawnability_color(color, block.block_light, block.sky_light),
+            _ => color,
+        }
+    }
+
+    fn uses_neighbors(&self) -> bool {
+        true
+    }
+}
+
+/// Textures mode darkened by the lack of light
+pub struct Night;
+
+impl RenderMode for Night {
+    fn name(&self) -> &str {
+        "night"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        let block = column.block;
+        night_color(
+            column.surface_color(true),
+            block.block_light,
+            block.sky_light,
+        )
+    }
+
+    fn uses_neighbors(&self) -> bool {
+        true
+    }
+}
+
+/// Textures mode drawing the top texture of each block, 16 pixels per block. Blocks without
+/// a texture use their flat color
+pub struct BlockTextures;
+
+impl RenderMode for BlockTextures {
+    fn name(&self) -> &str {
+        "blocktextures"
+    }
+
+    fn color(&self, column: &Column) -> [u8; 3] {
+        column.surface_color(true)
+    }
+
+    fn pixels_per_block(&self) -> u32 {
+        16
+    }
+
+    fn fill_pixels(&self, column: &Column, pixels: &mut [[u8; 3]]) {
+        let block = column.block;
+        let tints = column.tints();
+        let flat_color = column.block_color(&tints);
+
+        let textures = &column.renderer.block_textures;
+        let texture = if block.snowy {
+            textures.get("minecraft:snow")
+        } else {
+            textures.get(&namespaced_id(&block.name))
+        };
+
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let color = match texture {
+                Some(texture) => {
+                    texel_color(texture, i as u32 % 16, i as u32 / 16, flat_color, &tints)
+                }
+                None => flat_color,
+            };
+            *pixel = column.shade(color, &tints, true);
+        }
+    }
+
+    fn uses_neighbors(&self) -> bool {
+        true
+    }
+}
+
+/// Color of a texture pixel, tinted by the biome. Transparent pixels show the flat color of
+/// the block, as there is nothing drawn below the surface
+fn texel_color(
+    texture: &BlockTexture,
+    x: u32,
+    y: u32,
+    flat_color: [u8; 3],
+    tints: &BiomeTints,
+) -> [u8; 3] {
+    let Rgba([r, g, b, a]) = *texture.image.get_pixel(x, y);
+
+    let color = match texture.tint {
+        Some(source) => apply_tint([r, g, b], tints.get(source)),
+        None => [r, g, b],
+    };
+
+    let alpha = a as u32;
+    [0, 1, 2]
+        .map(|i| ((color[i] as u32 * alpha + flat_color[i] as u32 * (255 - alpha)) / 255) as u8)
+}
+
+/// Render modes by name, for the frontends
+#[derive(Clone)]
+pub struct RenderModes {
+    modes: Vec<Arc<dyn RenderMode>>,
+}
+
+impl Default for RenderModes {
+    /// The built-in modes
+    fn default() -> Self {
+        let mut modes = RenderModes { modes: Vec::new() };
+
+        modes.register(Textures);
+        modes.register(TexturesWithoutWater);
+        modes.register(Heightmap);
+        modes.register(Biomes);
+        modes.register(Temperature);
+        modes.register(Downfall);
+        modes.register(Inhabited);
+        modes.register(LastUpdated::default());
+        modes.register(BlockLight);
+        modes.register(Spawnability);
+        modes.register(Night);
+        modes.register(BlockTextures);
+
+        modes
+    }
+}
+
+impl RenderModes {
+    /// Add a mode, replacing the mode with the same name if any
+    pub fn register(&mut self, mode: impl RenderMode + 'static) {
+        let mode: Arc<dyn RenderMode> = Arc::new(mode);
+
+        match self.modes.iter_mut().find(|m| m.name() == mode.name()) {
+            Some(existing) => *existing = mode,
+            None => self.modes.push(mode),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn RenderMode>> {
+        self.modes.iter().find(|m| m.name() == name).cloned()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn RenderMode>> {
+        self.modes.iter()
+    }
+
+    /// Names of the modes, in the order they were registered
+    pub fn names(&self) -> Vec<String> {
+        self.modes.iter().map(|m| m.name().to_string()).collect()
+    }
+}
@@ -0,0 +1,82 @@
+use crate::fonts::{draw_text, put_pixel_checked};
+use anvilregion::{
+    chunks::Chunk,
+    structures::{get_chunk_structures, StructureStart},
+};
+use image::{Rgba, RgbaImage};
+
+/// Colors used for the outlines, picked from the structure id
+const STRUCTURE_COLORS: [[u8; 3]; 8] = [
+    [255, 85, 85],
+    [255, 170, 0],
+    [255, 255, 85],
+    [85, 255, 85],
+    [85, 255, 255],
+    [85, 85, 255],
+    [255, 85, 255],
+    [255, 255, 255],
+];
+
+/// Collect the structures starting in these chunks. See `read_overlapping_starts` to also
+/// get the structures starting in other regions
+pub fn collect_structure_starts(chunks: &[Chunk]) -> Result<Vec<StructureStart>, String> {
+    let mut starts = Vec::new();
+
+    for chunk in chunks {
+        starts.extend(get_chunk_structures(chunk)?.starts);
+    }
+
+    Ok(starts)
+}
+
+pub fn get_structure_color(id: &str) -> [u8; 3] {
+    let hash = id
+        .bytes()
+        .fold(0u32, |h, b| h.wrapping_mul(31).wrapping_add(b as u32));
+    STRUCTURE_COLORS[hash as usize % STRUCTURE_COLORS.len()]
+}
+
+/// Draw the outline and the name of each structure on a region image.
+/// Only the part of the structures inside the region is drawn
+pub fn draw_structure_overlay(
+    img: &mut RgbaImage,
+    region_x: i32,
+    region_z: i32,
+    starts: &[StructureStart],
+) {
+    // One or more pixels per block, depending on the render mode
+    let scale = (img.width() / 512).max(1) as i64;
+    let origin_x = region_x as i64 * 512;
+    let origin_z = region_z as i64 * 512;
+
+    for start in starts {
+        let bb = &start.bounding_box;
+        let [r, g, b] = get_structure_color(&start.id);
+        let color = Rgba([r, g, b, 255]);
+
+        let min_x = (bb.min_x as i64 - origin_x) * scale;
+        let max_x = (bb.max_x as i64 - origin_x + 1) * scale - 1;
+        let min_z = (bb.min_z as i64 - origin_z) * scale;
+        let max_z = (bb.max_z as i64 - origin_z + 1) * scale - 1;
+
+        for x in min_x..=max_x {
+            put_pixel_checked(img, x, min_z, color);
+            put_pixel_checked(img, x, max_z, color);
+        }
+        for z in min_z..=max_z {
+            put_pixel_checked(img, min_x, z, color);
+            put_pixel_checked(img, max_x, z, color);
+        }
+
+        let label = start.id.strip_prefix("minecraft:").unwrap_or(&start.id);
+        draw_text(
+            img,
+            label,
+            min_x + 3 * scale,
+            min_z + 3 * scale,
+            scale as u32 / 4,
+            color,
+            Rgba([0, 0, 0, 255]),
+        );
+    }
+}
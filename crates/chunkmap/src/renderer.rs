@@ -0,0 +1,444 @@
+use crate::{
+    biomes::{load_biomes_data, merge_biomes_data, BiomeData},
+    blocks::{load_block_colors, merge_block_colors},
+    hillshade::Hillshade,
+    images::RegionImages,
+    modes::{Column, RenderMode, Textures},
+    resource_packs::{BlockTexture, ResourcePacks},
+    tints::{TintEngine, TintGrid},
+    utils::{chunk_to_region_coords, get_biome_index},
+};
+use anvilregion::{
+    blocks::Block,
+    chunks::{parse_chunk_surface, Chunk, ChunkSurface},
+    dimensions::Dimension,
+    worlds::World,
+};
+use image::{ImageBuffer, Rgba, RgbaImage};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    error::Error,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub dimension: Dimension,
+    /// How the columns are colored, e.g. from `RenderModes`
+    pub render_mode: Arc<dyn RenderMode>,
+    /// Radius in blocks of the square the grass, foliage and water colors are averaged over,
+    /// like the biome blend option of the game. 0 to disable
+    pub blend_radius: u8,
+    /// Shading of the slopes, for every render mode. Replaces the shading of the textures
+    /// modes, which only compares the heights of the northern and southern blocks
+    pub hillshade: Option<Hillshade>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            dimension: Dimension::Overworld,
+            render_mode: Arc::new(Textures),
+            blend_radius: 0,
+            hillshade: None,
+        }
+    }
+}
+
+/// Renders chunks into images. The color tables and the biome data are parsed once when it
+/// is created, so a single renderer should be shared by every render, including across threads
+pub struct Renderer {
+    pub options: RenderOptions,
+    pub block_colors: HashMap<String, [u8; 3]>,
+    pub biomes: HashMap<String, BiomeData>,
+    /// Top textures of the blocks, for the block textures mode
+    pub block_textures: HashMap<String, BlockTexture>,
+    /// Grass, foliage and water colors of the tinted blocks
+    pub tints: TintEngine,
+    /// Unknown blocks and biomes already reported, so each one is only printed once
+    reported: Mutex<HashSet<String>>,
+}
+
+impl Renderer {
+    pub fn new(options: RenderOptions) -> Result<Renderer, Box<dyn Error>> {
+        Ok(Renderer {
+            options,
+            block_colors: load_block_colors()?,
+            biomes: load_biomes_data()?,
+            block_textures: HashMap::new(),
+            tints: TintEngine::default(),
+            reported: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Whether the name was not reported yet
+    fn report(&self, name: &str) -> bool {
+        match self.reported.lock() {
+            Ok(mut reported) => reported.insert(name.to_string()),
+            Err(_) => false,
+        }
+    }
+
+    fn report_unknown_blocks(&self, unknown_blocks: HashSet<String>) {
+        let new_blocks: Vec<_> = unknown_blocks
+            .into_iter()
+            .filter(|name| self.report(name))
+            .collect();
+
+        if !new_blocks.is_empty() {
+            eprintln!("Unknown blocks found: {new_blocks:?}");
+            eprintln!();
+        }
+    }
+
+    /// Layer a block palette over the current colors, e.g. for modded blocks.
+    /// See `merge_block_colors` for the format
+    pub fn add_block_palette(&mut self, json: &str) -> Result<usize, Box<dyn Error>> {
+        merge_block_colors(&mut self.block_colors, json)
+    }
+
+    /// Layer a biome palette over the current biomes, e.g. for modded biomes.
+    /// See `merge_biomes_data` for the format
+    pub fn add_biome_palette(&mut self, json: &str) -> Result<usize, Box<dyn Error>> {
+        merge_biomes_data(&mut self.biomes, json)
+    }
+
+    /// Load the block textures and the colormaps from resource packs, replacing the previous
+    /// ones. Returns the number of blocks with a texture
+    pub fn set_resource_packs(&mut self, packs: &ResourcePacks) -> Result<usize, Box<dyn Error>> {
+        self.block_textures = packs.block_textures()?;
+        self.tints.grass_colormap = packs.colormap("grass")?;
+        self.tints.foliage_colormap = packs.colormap("foliage")?;
+        Ok(self.block_textures.len())
+    }
+
+    /// Data of a biome from its namespaced id. Unknown biomes use the plains data
+    pub fn biome(&self, name: &str) -> &BiomeData {
+        self.biomes.get(name).unwrap_or_else(|| {
+            if self.report(&format!("biome:{name}")) {
+                eprintln!("No biome data found for '{name}'");
+            }
+            self.biomes
+                .get("minecraft:plains")
+                .expect("No 'plains' biome found")
+        })
+    }
+
+    /// Create a region image from its chunks, with the render mode of the options.
+    /// Can create multiple images if all the chunks are not in the same region
+    pub fn render_chunks(&self, chunks: &[Chunk]) -> Result<RegionImages, Box<dyn Error>> {
+        self.render_chunks_as(chunks, &[], self.options.render_mode.as_ref())
+    }
+
+    /// Same as `render_chunks`, with the chunks around them that are not drawn but used to
+    /// blend the biome colors and shade the edges, e.g. from `World::read_border_chunks`
+    pub fn render_chunks_with_neighbors(
+        &self,
+        chunks: &[Chunk],
+        neighbors: &[Chunk],
+    ) -> Result<RegionImages, Box<dyn Error>> {
+        self.render_chunks_as(chunks, neighbors, self.options.render_mode.as_ref())
+    }
+
+    /// Whether the renders use the chunks around the rendered ones, to blend the biome colors
+    /// and shade the edges
+    pub fn needs_neighbors(&self) -> bool {
+        self.uses_neighbors(self.options.render_mode.as_ref())
+    }
+
+    /// Whether the renders in a mode use the chunks around the rendered ones
+    pub fn uses_neighbors(&self, mode: &dyn RenderMode) -> bool {
+        mode.uses_neighbors() || self.options.hillshade.is_some()
+    }
+
+    /// Light factor of the hillshade for a column, from the heights of the water surface or
+    /// of the blocks around it. The missing blocks have the height of the column
+    fn hillshade_factor(
+        &self,
+        block: &Block,
+        block_map: &HashMap<(i32, i32), &Block>,
+    ) -> Option<f32> {
+        let hillshade = self.options.hillshade.as_ref()?;
+        let top = |b: &Block| (b.position.y + b.depth as i32) as f32;
+
+        let mut heights = [[top(block); 3]; 3];
+        for (dz, row) in heights.iter_mut().enumerate() {
+            for (dx, height) in row.iter_mut().enumerate() {
+                let position = (
+                    block.position.x + dx as i32 - 1,
+                    block.position.z + dz as i32 - 1,
+                );
+                if let Some(b) = block_map.get(&position) {
+                    *height = top(b);
+                }
+            }
+        }
+
+        Some(hillshade.factor(&heights))
+    }
+
+    /// Apply the hillshade, if enabled
+    fn hillshade_color(&self, color: [u8; 3], factor: Option<f32>) -> [u8; 3] {
+        match (&self.options.hillshade, factor) {
+            (Some(hillshade), Some(factor)) => hillshade.apply(color, factor),
+            _ => color,
+        }
+    }
+
+    /// Width in chunks of the border of neighbouring chunks used by the renders
+    pub fn neighbors_width(&self) -> i32 {
+        (self.options.blend_radius.max(1) as i32 + 15) / 16
+    }
+
+    /// Surfaces of the chunks out of a region within `margin` blocks of it
+    fn surfaces_around<'a>(
+        &self,
+        (rx, rz): (i32, i32),
+        margin: i32,
+        chunks: impl Iterator<Item = &'a Chunk>,
+    ) -> Result<Vec<ChunkSurface>, Box<dyn Error>> {
+        let min_x = rx * 512 - margin;
+        let min_z = rz * 512 - margin;
+        let max_x = rx * 512 + 511 + margin;
+        let max_z = rz * 512 + 511 + margin;
+
+        let mut surfaces = Vec::new();
+        for chunk in chunks {
+            let x = chunk.position.x * 16;
+            let z = chunk.position.z * 16;
+            let inside = x + 15 >= min_x && x <= max_x && z + 15 >= min_z && z <= max_z;
+
+            if inside && chunk_to_region_coords(chunk.position.x, chunk.position.z) != (rx, rz) {
+                surfaces.push(parse_chunk_surface(chunk, &self.options.dimension)?);
+            }
+        }
+
+        Ok(surfaces)
+    }
+
+    /// Blended tints of the columns of a region and of the columns around it
+    fn blended_tints(&self, (rx, rz): (i32, i32), surfaces: &[&ChunkSurface]) -> TintGrid {
+        let radius = self.options.blend_radius as i32;
+        let size = 512 + 2 * radius as usize;
+        let mut grid = TintGrid::new(rx * 512 - radius, rz * 512 - radius, size, size);
+
+        for surface in surfaces {
+            for block in &surface.blocks {
+                let (x, z) = (block.position.x, block.position.z);
+                let biome_index = get_biome_index(x & 0xF, z & 0xF);
+                let biome_data = self.biome(&surface.biomes[biome_index]);
+                grid.set(x, z, self.tints.biome_tints(biome_data, x, z));
+            }
+        }
+
+        grid.blend(radius as usize)
+    }
+
+    /// Same as `render_chunks_with_neighbors` with another render mode
+    pub fn render_chunks_as(
+        &self,
+        chunks: &[Chunk],
+        neighbors: &[Chunk],
+        mode: &dyn RenderMode,
+    ) -> Result<RegionImages, Box<dyn Error>> {
+        let mut images = self.render_chunks_in_modes(chunks, neighbors, &[mode])?;
+        Ok(images.remove(0))
+    }
+
+    /// Same as `render_chunks_as` with several render modes at once. The surface of each
+    /// chunk is only parsed once. Returns the images of each mode, in the same order
+    pub fn render_chunks_in_modes(
+        &self,
+        chunks: &[Chunk],
+        neighbors: &[Chunk],
+        modes: &[&dyn RenderMode],
+    ) -> Result<Vec<RegionImages>, Box<dyn Error>> {
+        let dimension = &self.options.dimension;
+        let uses_neighbors = modes.iter().any(|mode| self.uses_neighbors(*mode));
+        let uses_tints = modes.iter().any(|mode| mode.uses_neighbors());
+
+        // Group by region
+
+        let mut regions: HashMap<(i32, i32), Vec<&Chunk>> = HashMap::new();
+        for chunk in chunks {
+            let region_coords = chunk_to_region_coords(chunk.position.x, chunk.position.z);
+            regions.entry(region_coords).or_default().push(chunk);
+        }
+
+        // Generate images
+
+        let mut images: Vec<RegionImages> = modes.iter().map(|_| Vec::new()).collect();
+
+        for ((rx, rz), region_chunks) in &regions {
+            let min_x = rx * 32;
+            let min_z = rz * 32;
+            let width = 32 * 16;
+            let height = 32 * 16;
+
+            let unknown_blocks = RefCell::new(HashSet::new());
+            let mut imgs: Vec<ImageBuffer<Rgba<u8>, Vec<u8>>> = modes
+                .iter()
+                .map(|mode| {
+                    let scale = mode.pixels_per_block();
+                    ImageBuffer::from_pixel(
+                        width as u32 * scale,
+                        height as u32 * scale,
+                        Rgba([0, 0, 0, 0]),
+                    )
+                })
+                .collect();
+            let mut pixels = Vec::new();
+
+            let surfaces = region_chunks
+                .iter()
+                .map(|chunk| Ok((*chunk, parse_chunk_surface(chunk, dimension)?)))
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+            // The chunks around the region are only used by the textures modes and the hillshade
+            let other_surfaces = if uses_neighbors {
+                let margin = (self.options.blend_radius as i32).max(1);
+                self.surfaces_around((*rx, *rz), margin, chunks.iter().chain(neighbors))?
+            } else {
+                Vec::new()
+            };
+            let all_surfaces: Vec<&ChunkSurface> = surfaces
+                .iter()
+                .map(|(_, surface)| surface)
+                .chain(&other_surfaces)
+                .collect();
+
+            let tint_grid = (uses_tints && self.options.blend_radius > 0)
+                .then(|| self.blended_tints((*rx, *rz), &all_surfaces));
+
+            // Heights of the region and of the blocks around it, so the edges of the chunks
+            // and of the region are shaded too
+            let mut block_map = HashMap::new();
+            if uses_neighbors {
+                for surface in &all_surfaces {
+                    for b in &surface.blocks {
+                        block_map.insert((b.position.x, b.position.z), b);
+                    }
+                }
+            }
+            let no_neighbors = HashMap::new();
+
+            for (chunk, surface) in &surfaces {
+                for block in &surface.blocks {
+                    let local_x = block.position.x - min_x * 16;
+                    let local_z = block.position.z - min_z * 16;
+                    if local_x < 0 || local_x >= width || local_z < 0 || local_z >= height {
+                        continue;
+                    }
+
+                    let pixel_x = local_x as u32;
+                    let pixel_y = local_z as u32;
+                    let biome_index =
+                        get_biome_index(block.position.x & 0xF, block.position.z & 0xF);
+                    let biome = self.biome(&surface.biomes[biome_index]);
+                    let light = self.hillshade_factor(block, &block_map);
+
+                    for (mode, img) in modes.iter().zip(&mut imgs) {
+                        let column = Column {
+                            block,
+                            biome,
+                            chunk,
+                            neighbors: match mode.uses_neighbors() {
+                                true => &block_map,
+                                false => &no_neighbors,
+                            },
+                            renderer: self,
+                            tint_grid: tint_grid.as_ref().filter(|_| mode.uses_neighbors()),
+                            unknown_blocks: &unknown_blocks,
+                        };
+
+                        let scale = mode.pixels_per_block();
+                        if scale == 1 {
+                            let [r, g, b] = self.hillshade_color(mode.color(&column), light);
+                            img.put_pixel(pixel_x, pixel_y, Rgba([r, g, b, 255]));
+                            continue;
+                        }
+
+                        pixels.resize((scale * scale) as usize, [0; 3]);
+                        mode.fill_pixels(&column, &mut pixels);
+                        for (i, color) in pixels.iter().enumerate() {
+                            let [r, g, b] = self.hillshade_color(*color, light);
+                            img.put_pixel(
+                                pixel_x * scale + i as u32 % scale,
+                                pixel_y * scale + i as u32 / scale,
+                                Rgba([r, g, b, 255]),
+                            );
+                        }
+                    }
+                }
+            }
+
+            self.report_unknown_blocks(unknown_blocks.into_inner());
+
+            for (mode_images, img) in images.iter_mut().zip(imgs) {
+                mode_images.push((*rx, *rz, img));
+            }
+        }
+
+        Ok(images)
+    }
+
+    /// Render the blocks from `min_x` `min_z` to `max_x` `max_z` (inclusive) of a world in a
+    /// single image, with the size of the blocks of the render mode. Missing chunks are
+    /// transparent
+    pub fn render_area(
+        &self,
+        world: &World,
+        (min_x, min_z): (i32, i32),
+        (max_x, max_z): (i32, i32),
+    ) -> Result<RgbaImage, Box<dyn Error>> {
+        let scale = self.options.render_mode.pixels_per_block() as i32;
+        let width = ((max_x - min_x + 1) * scale) as u32;
+        let height = ((max_z - min_z + 1) * scale) as u32;
+        let mut area_img = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+
+        let chunk_range_x = min_x.div_euclid(16)..=max_x.div_euclid(16);
+        let chunk_range_z = min_z.div_euclid(16)..=max_z.div_euclid(16);
+
+        for info in &world.regions {
+            if info.x * 512 > max_x
+                || info.x * 512 + 511 < min_x
+                || info.z * 512 > max_z
+                || info.z * 512 + 511 < min_z
+            {
+                continue;
+            }
+
+            let region = world.read_region_file(info)?;
+            let (chunks, mut neighbors): (Vec<_>, Vec<_>) =
+                region.chunks.into_iter().partition(|c| {
+                    chunk_range_x.contains(&c.position.x) && chunk_range_z.contains(&c.position.z)
+                });
+
+            if self.needs_neighbors() {
+                neighbors.extend(world.read_border_chunks(
+                    info.x,
+                    info.z,
+                    self.neighbors_width(),
+                )?);
+            } else {
+                neighbors.clear();
+            }
+
+            for (rx, rz, img) in self.render_chunks_with_neighbors(&chunks, &neighbors)? {
+                for (px, pz, pixel) in img.enumerate_pixels() {
+                    let x = rx * 512 + px as i32 / scale;
+                    let z = rz * 512 + pz as i32 / scale;
+
+                    if x >= min_x && x <= max_x && z >= min_z && z <= max_z {
+                        let area_x = (x - min_x) * scale + px as i32 % scale;
+                        let area_z = (z - min_z) * scale + pz as i32 % scale;
+                        area_img.put_pixel(area_x as u32, area_z as u32, *pixel);
+                    }
+                }
+            }
+        }
+
+        Ok(area_img)
+    }
+}
@@ -0,0 +1,434 @@
+use crate::{
+    blocks::BLOCKS_JSON,
+    tints::{block_tint_source, BiomeTints, Colormap, TintSource},
+    utils::u32_to_rgb,
+};
+use anvilregion::archives::Archive;
+use image::RgbaImage;
+use serde_json::Value as JsonValue;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    fs::{read, read_dir},
+    path::{Path, PathBuf},
+};
+
+/// Plains colors, used for the tinted faces of the blocks that are not tinted by the renderer
+const GRASS_TINT: u32 = 0x91bd59;
+const FOLIAGE_TINT: u32 = 0x77ab2f;
+const WATER_TINT: u32 = 0x3f76e4;
+
+/// Texture keys of the top face, for the models whose elements are not in the packs
+const TOP_TEXTURE_KEYS: [&str; 7] = ["top", "end", "all", "texture", "cross", "plant", "particle"];
+
+/// Parents are followed up to this depth, in case of cycles
+const MAX_MODEL_DEPTH: usize = 32;
+
+enum PackFiles {
+    Folder(PathBuf),
+    Archive(Archive),
+}
+
+/// A resource pack: a folder, a zip file, or the client jar for the vanilla assets
+pub struct ResourcePack {
+    files: PackFiles,
+    /// Folder containing `assets`, for packs zipped with their folder
+    prefix: String,
+    /// Paths of the files, relative to the prefix
+    paths: BTreeSet<String>,
+}
+
+fn list_folder(
+    root: &Path,
+    folder: &Path,
+    paths: &mut BTreeSet<String>,
+) -> Result<(), Box<dyn Error>> {
+    for entry in read_dir(folder)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            list_folder(root, &path, paths)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            paths.insert(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+
+    Ok(())
+}
+
+impl ResourcePack {
+    /// Open a resource pack folder, or a `.zip` or `.jar` file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<ResourcePack, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut all_paths = BTreeSet::new();
+
+        let files = if path.is_dir() {
+            list_folder(path, path, &mut all_paths)?;
+            PackFiles::Folder(path.to_path_buf())
+        } else {
+            let archive = Archive::open(path)?;
+            all_paths.extend(archive.files().map(|(p, _)| p.to_string()));
+            PackFiles::Archive(archive)
+        };
+
+        // The shortest path to an `assets` folder
+        let prefix = all_paths
+            .iter()
+            .filter_map(|p| {
+                if p.starts_with("assets/") {
+                    Some("")
+                } else {
+                    p.find("/assets/").map(|i| &p[..i + 1])
+                }
+            })
+            .min_by_key(|p| p.len())
+            .ok_or_else(|| format!("No 'assets' folder in '{}'", path.display()))?
+            .to_string();
+
+        let paths = all_paths
+            .iter()
+            .filter_map(|p| p.strip_prefix(&prefix).map(|p| p.to_string()))
+            .collect();
+
+        Ok(ResourcePack {
+            files,
+            prefix,
+            paths,
+        })
+    }
+
+    /// Read a file of the pack, e.g. `assets/minecraft/blockstates/stone.json`
+    pub fn read(&self, path: &str) -> Option<Vec<u8>> {
+        if !self.paths.contains(path) {
+            return None;
+        }
+
+        let full_path = format!("{}{path}", self.prefix);
+
+        match &self.files {
+            PackFiles::Folder(folder) => read(folder.join(full_path)).ok(),
+            PackFiles::Archive(archive) => archive.read(&full_path).ok(),
+        }
+    }
+
+    /// Namespaced ids of the blocks with a blockstate file
+    pub fn block_ids(&self) -> Vec<String> {
+        self.paths
+            .iter()
+            .filter_map(|p| {
+                let rest = p.strip_prefix("assets/")?;
+                let (namespace, file) = rest.split_once("/blockstates/")?;
+                let name = file.strip_suffix(".json")?;
+                (!name.contains('/')).then(|| format!("{namespace}:{name}"))
+            })
+            .collect()
+    }
+}
+
+/// Split a resource location like `minecraft:block/stone` or `block/stone`
+fn split_location(location: &str) -> (&str, &str) {
+    location.split_once(':').unwrap_or(("minecraft", location))
+}
+
+/// Textures and elements of a model, merged with its parents
+#[derive(Default)]
+struct ResolvedModel {
+    textures: HashMap<String, String>,
+    elements: Option<Vec<JsonValue>>,
+}
+
+/// Top face texture of a block, 16x16 pixels
+#[derive(Debug, Clone)]
+pub struct BlockTexture {
+    pub image: RgbaImage,
+    /// Tint of the block if its top face is tinted
+    pub tint: Option<TintSource>,
+}
+
+/// Several resource packs, the last one having the highest priority like in the game
+pub struct ResourcePacks {
+    pub packs: Vec<ResourcePack>,
+}
+
+impl ResourcePacks {
+    pub fn open<P: AsRef<Path>>(paths: &[P]) -> Result<ResourcePacks, Box<dyn Error>> {
+        let packs = paths
+            .iter()
+            .map(ResourcePack::open)
+            .collect::<Result<_, _>>()?;
+
+        Ok(ResourcePacks { packs })
+    }
+
+    fn read(&self, path: &str) -> Option<Vec<u8>> {
+        self.packs.iter().rev().find_map(|pack| pack.read(path))
+    }
+
+    fn read_json(&self, path: &str) -> Option<JsonValue> {
+        serde_json::from_slice(&self.read(path)?).ok()
+    }
+
+    /// Model of the default variant of a block: the first variant, or the first multipart
+    /// entry that is always applied
+    fn block_model(&self, block_id: &str) -> Option<String> {
+        let (namespace, name) = split_location(block_id);
+        let blockstate = self.read_json(&format!("assets/{namespace}/blockstates/{name}.json"))?;
+
+        let apply = if let Some(variants) = blockstate.get("variants").and_then(|v| v.as_object()) {
+            variants.get("").or_else(|| variants.values().next())?
+        } else {
+            let parts = blockstate.get("multipart")?.as_array()?;
+            let part = parts
+                .iter()
+                .find(|p| p.get("when").is_none())
+                .or_else(|| parts.first())?;
+            part.get("apply")?
+        };
+
+        // A list of weighted models
+        let apply = match apply {
+            JsonValue::Array(list) => list.first()?,
+            v => v,
+        };
+
+        apply.get("model")?.as_str().map(|m| m.to_string())
+    }
+
+    fn resolve_model(&self, model_id: &str) -> ResolvedModel {
+        let mut resolved = ResolvedModel::default();
+        let mut current = Some(model_id.to_string());
+
+        for _ in 0..MAX_MODEL_DEPTH {
+            let Some(id) = current.take() else {
+                break;
+            };
+
+            let (namespace, path) = split_location(&id);
+            let Some(model) = self.read_json(&format!("assets/{namespace}/models/{path}.json"))
+            else {
+                break;
+            };
+
+            // The textures of the children override the ones of their parents
+            if let Some(textures) = model.get("textures").and_then(|t| t.as_object()) {
+                for (key, value) in textures {
+                    if let Some(value) = value.as_str() {
+                        resolved
+                            .textures
+                            .entry(key.clone())
+                            .or_insert_with(|| value.to_string());
+                    }
+                }
+            }
+
+            if resolved.elements.is_none() {
+                if let Some(elements) = model.get("elements").and_then(|e| e.as_array()) {
+                    resolved.elements = Some(elements.clone());
+                }
+            }
+
+            current = model
+                .get("parent")
+                .and_then(|p| p.as_str())
+                .map(|p| p.to_string());
+        }
+
+        resolved
+    }
+
+    /// Texture of the top face of a model and whether it is tinted. The top face is the
+    /// `up` face of the highest element
+    fn top_texture(&self, model: &ResolvedModel) -> Option<(String, bool)> {
+        let top_face = model.elements.as_ref().and_then(|elements| {
+            elements
+                .iter()
+                .filter_map(|element| {
+                    let top = element.get("to")?.as_array()?.get(1)?.as_f64()?;
+                    let face = element.get("faces")?.get("up")?;
+                    Some((top, face))
+                })
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, face)| face)
+        });
+
+        let (mut texture, tinted) = match top_face {
+            Some(face) => (
+                face.get("texture")?.as_str()?.to_string(),
+                face.get("tintindex").is_some(),
+            ),
+            None => {
+                let key = TOP_TEXTURE_KEYS
+                    .iter()
+                    .find(|key| model.textures.contains_key(**key))?;
+                (format!("#{key}"), false)
+            }
+        };
+
+        // Follow the references to other texture variables
+        for _ in 0..MAX_MODEL_DEPTH {
+            match texture.strip_prefix('#') {
+                Some(key) => texture = model.textures.get(key)?.clone(),
+                None => return Some((texture, tinted)),
+            }
+        }
+
+        None
+    }
+
+    /// First frame of a texture, animated textures being a vertical strip of square frames
+    fn load_texture(&self, texture: &str) -> Option<RgbaImage> {
+        let (namespace, path) = split_location(texture);
+        let bytes = self.read(&format!("assets/{namespace}/textures/{path}.png"))?;
+        let image = image::load_from_memory(&bytes).ok()?.to_rgba8();
+
+        let size = image.width().min(image.height());
+        Some(image::imageops::crop_imm(&image, 0, 0, size, size).to_image())
+    }
+
+    /// Texture of the top face of a block and whether it is tinted, from its model or from a
+    /// texture with its name
+    fn block_top_texture(&self, block_id: &str) -> Option<(RgbaImage, bool)> {
+        let (namespace, name) = split_location(block_id);
+
+        let from_model = self.block_model(block_id).and_then(|model_id| {
+            let model = self.resolve_model(&model_id);
+            let (texture, tinted) = self.top_texture(&model)?;
+            Some((self.load_texture(&texture)?, tinted))
+        });
+
+        from_model.or_else(|| {
+            [
+                format!("{namespace}:block/{name}_top"),
+                format!("{namespace}:block/{name}"),
+            ]
+            .iter()
+            .find_map(|texture| self.load_texture(texture))
+            .map(|image| (image, false))
+        })
+    }
+
+    /// Color of a block: the average of its top texture. The renderer multiplies the colors
+    /// of the blocks tinted by the biome, the other tinted blocks use the plains colors
+    pub fn block_color(&self, block_id: &str) -> Option<[u8; 3]> {
+        let (namespace, name) = split_location(block_id);
+        let (image, tinted) = self.block_top_texture(block_id)?;
+        let color = average_color(&image)?;
+
+        let biome_tinted = namespace == "minecraft"
+            && block_tint_source(name).is_some_and(|source| source.is_biome());
+
+        Some(if tinted && !biome_tinted {
+            apply_tint(color, default_tint(name))
+        } else {
+            color
+        })
+    }
+
+    /// Top texture of a block, scaled to 16x16 pixels, with the tint the renderer applies
+    pub fn block_texture(&self, block_id: &str) -> Option<BlockTexture> {
+        let (_, name) = split_location(block_id);
+        let (image, tinted) = self.block_top_texture(block_id)?;
+
+        let image = if image.width() == 16 {
+            image
+        } else {
+            image::imageops::resize(&image, 16, 16, image::imageops::FilterType::Triangle)
+        };
+
+        // The water model has no elements, only a texture
+        let tint = block_tint_source(name).filter(|_| tinted || name == "water");
+
+        Some(BlockTexture { image, tint })
+    }
+
+    /// Ids of the blocks with a blockstate in the packs and of the vanilla blocks
+    fn all_block_ids(&self) -> Result<BTreeSet<String>, Box<dyn Error>> {
+        let vanilla: HashMap<String, String> = serde_json::from_str(BLOCKS_JSON)?;
+
+        let mut block_ids: BTreeSet<String> = vanilla
+            .keys()
+            .map(|name| format!("minecraft:{name}"))
+            .collect();
+        for pack in &self.packs {
+            block_ids.extend(pack.block_ids());
+        }
+
+        Ok(block_ids)
+    }
+
+    /// A colormap of the packs, `grass` or `foliage`
+    pub fn colormap(&self, name: &str) -> Result<Option<Colormap>, Box<dyn Error>> {
+        match self.read(&format!("assets/minecraft/textures/colormap/{name}.png")) {
+            Some(bytes) => Ok(Some(Colormap::from_png(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Colors of the blocks with a blockstate in the packs and of the vanilla blocks,
+    /// keyed by namespaced block ids. Blocks without a texture are left out
+    pub fn block_colors(&self) -> Result<BTreeMap<String, [u8; 3]>, Box<dyn Error>> {
+        Ok(self
+            .all_block_ids()?
+            .into_iter()
+            .filter_map(|id| self.block_color(&id).map(|color| (id, color)))
+            .collect())
+    }
+
+    /// Top textures of the blocks with a blockstate in the packs and of the vanilla blocks,
+    /// keyed by namespaced block ids
+    pub fn block_textures(&self) -> Result<HashMap<String, BlockTexture>, Box<dyn Error>> {
+        Ok(self
+            .all_block_ids()?
+            .into_iter()
+            .filter_map(|id| self.block_texture(&id).map(|texture| (id, texture)))
+            .collect())
+    }
+}
+
+/// Average color of a texture, weighted by the alpha of each pixel
+fn average_color(image: &RgbaImage) -> Option<[u8; 3]> {
+    let mut sums = [0f64; 3];
+    let mut total_alpha = 0f64;
+
+    for pixel in image.pixels() {
+        let alpha = pixel[3] as f64 / 255.0;
+
+        for (sum, channel) in sums.iter_mut().zip(pixel.0) {
+            *sum += channel as f64 * alpha;
+        }
+        total_alpha += alpha;
+    }
+
+    if total_alpha == 0.0 {
+        return None;
+    }
+
+    Some(sums.map(|sum| (sum / total_alpha).round() as u8))
+}
+
+/// Tint of the tinted faces of a block not tinted by the biome in the renderer. The plains
+/// colors are used for the modded blocks
+fn default_tint(name: &str) -> [u8; 3] {
+    let plains = BiomeTints {
+        grass: u32_to_rgb(GRASS_TINT),
+        foliage: u32_to_rgb(FOLIAGE_TINT),
+        water: u32_to_rgb(WATER_TINT),
+    };
+
+    plains.get(block_tint_source(name).unwrap_or(TintSource::Grass))
+}
+
+/// Multiply a grey texture color by a tint, like the game does
+pub fn apply_tint(color: [u8; 3], tint: [u8; 3]) -> [u8; 3] {
+    [0, 1, 2].map(|i| ((color[i] as u32 * tint[i] as u32) / 255) as u8)
+}
+
+/// Encode colors in the format of `blocks.json`, to be loaded as a palette
+pub fn colors_to_json(colors: &BTreeMap<String, [u8; 3]>) -> Result<String, Box<dyn Error>> {
+    let hex: BTreeMap<&String, String> = colors
+        .iter()
+        .map(|(id, [r, g, b])| (id, format!("#{r:02x}{g:02x}{b:02x}")))
+        .collect();
+
+    Ok(serde_json::to_string_pretty(&hex)?)
+}
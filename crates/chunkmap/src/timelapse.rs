@@ -0,0 +1,74 @@
+use crate::fonts::{draw_text, GLYPH_HEIGHT};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, Rgba, RgbaImage,
+};
+use std::error::Error;
+
+/// Write a caption in the bottom left corner of a frame, scaled with the frame width
+pub fn draw_caption(img: &mut RgbaImage, text: &str) {
+    let scale = (img.width() / 256).clamp(1, 4);
+    let margin = 2 * scale as i64;
+    let y = img.height() as i64 - (GLYPH_HEIGHT * scale) as i64 - margin;
+
+    draw_text(
+        img,
+        text,
+        margin,
+        y,
+        scale,
+        Rgba([255, 255, 255, 255]),
+        Rgba([0, 0, 0, 255]),
+    );
+}
+
+fn check_frames(frames: &[RgbaImage]) -> Result<(u32, u32), Box<dyn Error>> {
+    let first = frames.first().ok_or("No frames to encode")?;
+    let size = first.dimensions();
+
+    if frames.iter().any(|f| f.dimensions() != size) {
+        return Err("All frames must have the same size".into());
+    }
+
+    Ok(size)
+}
+
+/// Encode the frames as an animated PNG that loops forever
+pub fn encode_apng(frames: &[RgbaImage], delay_ms: u16) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (width, height) = check_frames(frames)?;
+    let mut data = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut data, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0)?;
+    encoder.set_frame_delay(delay_ms, 1000)?;
+
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        writer.write_image_data(frame.as_raw())?;
+    }
+    writer.finish()?;
+
+    Ok(data)
+}
+
+/// Encode the frames as a GIF that loops forever. GIFs only have 256 colors per frame
+pub fn encode_gif(frames: &[RgbaImage], delay_ms: u16) -> Result<Vec<u8>, Box<dyn Error>> {
+    check_frames(frames)?;
+    let mut data = Vec::new();
+
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut data, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        let delay = Delay::from_numer_denom_ms(delay_ms as u32, 1);
+        encoder.encode_frames(
+            frames
+                .iter()
+                .map(|f| Frame::from_parts(f.clone(), 0, 0, delay)),
+        )?;
+    }
+
+    Ok(data)
+}
@@ -0,0 +1,449 @@
+use crate::{biomes::BiomeData, utils::u32_to_rgb};
+use std::error::Error;
+
+/// Source of the color a block is tinted with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TintSource {
+    Grass,
+    Foliage,
+    Water,
+    Fixed(u32),
+}
+
+impl TintSource {
+    /// Whether the color depends on the biome
+    pub fn is_biome(&self) -> bool {
+        !matches!(self, TintSource::Fixed(_))
+    }
+}
+
+/// Tint of the vanilla blocks tinted in the game, by name without namespace
+pub fn block_tint_source(name: &str) -> Option<TintSource> {
+    match name {
+        "grass_block" | "short_grass" | "tall_grass" | "fern" | "large_fern" | "potted_fern"
+        | "bush" | "sugar_cane" => Some(TintSource::Grass),
+        "oak_leaves" | "jungle_leaves" | "acacia_leaves" | "dark_oak_leaves"
+        | "mangrove_leaves" | "vine" => Some(TintSource::Foliage),
+        "water" | "bubble_column" | "water_cauldron" => Some(TintSource::Water),
+        "birch_leaves" => Some(TintSource::Fixed(0x80a755)),
+        "spruce_leaves" => Some(TintSource::Fixed(0x619961)),
+        "lily_pad" => Some(TintSource::Fixed(0x208030)),
+        "attached_melon_stem" | "attached_pumpkin_stem" => Some(TintSource::Fixed(0xe0c71c)),
+        // Unpowered
+        "redstone_wire" => Some(TintSource::Fixed(0x4c0000)),
+        _ => None,
+    }
+}
+
+/// Change of the grass color by some biomes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GrassModifier {
+    None,
+    DarkForest,
+    /// Two colors picked with the biome info noise
+    Swamp,
+}
+
+/// Colors set by the vanilla biomes instead of the colormaps: grass override, foliage
+/// override and grass modifier
+fn biome_effects(name: &str) -> (Option<u32>, Option<u32>, GrassModifier) {
+    match name {
+        "minecraft:swamp" => (None, Some(0x6a7039), GrassModifier::Swamp),
+        "minecraft:mangrove_swamp" => (None, Some(0x8db127), GrassModifier::Swamp),
+        "minecraft:dark_forest" => (None, None, GrassModifier::DarkForest),
+        "minecraft:badlands" | "minecraft:eroded_badlands" | "minecraft:wooded_badlands" => {
+            (Some(0x90814d), Some(0x9e814d), GrassModifier::None)
+        }
+        "minecraft:cherry_grove" => (Some(0xb6db61), Some(0xb6db61), GrassModifier::None),
+        "minecraft:pale_garden" => (Some(0x778272), Some(0x878d76), GrassModifier::None),
+        _ => (None, None, GrassModifier::None),
+    }
+}
+
+const SWAMP_GRASS_COLD: u32 = 0x4c763c;
+const SWAMP_GRASS_WARM: u32 = 0x6a7039;
+
+/// Color of the colormap pixels out of the triangle
+const COLORMAP_MISSING: [u8; 3] = [0xff, 0x00, 0xff];
+
+/// A 256x256 colormap of a resource pack, `grass.png` or `foliage.png`
+#[derive(Debug, Clone)]
+pub struct Colormap {
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Colormap {
+    pub fn from_png(bytes: &[u8]) -> Result<Colormap, Box<dyn Error>> {
+        let image = image::load_from_memory(bytes)?.to_rgb8();
+        if image.width() != 256 || image.height() != 256 {
+            return Err(format!(
+                "Colormaps must be 256x256, found {}x{}",
+                image.width(),
+                image.height()
+            )
+            .into());
+        }
+
+        Ok(Colormap {
+            pixels: image.pixels().map(|p| p.0).collect(),
+        })
+    }
+
+    /// Color for a climate, like the game: the downfall is scaled by the temperature
+    pub fn get(&self, temperature: f32, downfall: f32) -> [u8; 3] {
+        let temperature = temperature.clamp(0.0, 1.0) as f64;
+        let downfall = downfall.clamp(0.0, 1.0) as f64 * temperature;
+
+        let x = ((1.0 - temperature) * 255.0) as usize;
+        let y = ((1.0 - downfall) * 255.0) as usize;
+
+        *self.pixels.get(y << 8 | x).unwrap_or(&COLORMAP_MISSING)
+    }
+}
+
+/// Tint colors of a block column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiomeTints {
+    pub grass: [u8; 3],
+    pub foliage: [u8; 3],
+    pub water: [u8; 3],
+}
+
+impl BiomeTints {
+    pub fn get(&self, source: TintSource) -> [u8; 3] {
+        match source {
+            TintSource::Grass => self.grass,
+            TintSource::Foliage => self.foliage,
+            TintSource::Water => self.water,
+            TintSource::Fixed(color) => u32_to_rgb(color),
+        }
+    }
+}
+
+/// Computes the grass, foliage and water colors of each column like the game. Without
+/// colormaps, the colors of the biome data are used, which are the vanilla colormap colors
+/// with the overrides and modifiers already applied. The swamp grass depends on the position
+pub struct TintEngine {
+    pub grass_colormap: Option<Colormap>,
+    pub foliage_colormap: Option<Colormap>,
+    noise: SimplexNoise,
+}
+
+impl Default for TintEngine {
+    fn default() -> Self {
+        TintEngine {
+            grass_colormap: None,
+            foliage_colormap: None,
+            noise: SimplexNoise::new(&mut JavaRandom::new(BIOME_INFO_SEED)),
+        }
+    }
+}
+
+impl TintEngine {
+    /// Tints of the column at `x` `z`. Biomes of other namespaces keep their colors,
+    /// as most mods set them
+    pub fn biome_tints(&self, biome: &BiomeData, x: i32, z: i32) -> BiomeTints {
+        let (grass_override, foliage_override, modifier) = biome_effects(&biome.name);
+        let vanilla = biome.name.starts_with("minecraft:");
+
+        let grass = match (&self.grass_colormap, grass_override) {
+            (_, Some(color)) => color,
+            (Some(colormap), None) if vanilla => {
+                let [r, g, b] = colormap.get(biome.temperature, biome.downfall);
+                match modifier {
+                    GrassModifier::DarkForest => {
+                        ((u32::from_be_bytes([0, r, g, b]) & 0xfefefe) + 0x28340a) >> 1
+                    }
+                    _ => u32::from_be_bytes([0, r, g, b]),
+                }
+            }
+            _ => biome.grass_color,
+        };
+
+        let grass = match modifier {
+            GrassModifier::Swamp => {
+                let noise = self.noise.get_value(x as f64 * 0.0225, z as f64 * 0.0225);
+                if noise < -0.1 {
+                    SWAMP_GRASS_COLD
+                } else {
+                    SWAMP_GRASS_WARM
+                }
+            }
+            _ => grass,
+        };
+
+        let foliage = match (&self.foliage_colormap, foliage_override) {
+            (_, Some(color)) => u32_to_rgb(color),
+            (Some(colormap), None) if vanilla => colormap.get(biome.temperature, biome.downfall),
+            _ => u32_to_rgb(biome.foliage_color),
+        };
+
+        BiomeTints {
+            grass: u32_to_rgb(grass),
+            foliage,
+            water: u32_to_rgb(biome.water_color),
+        }
+    }
+}
+
+/// Tints of an area of columns, to blend them like the biome blend option of the game
+pub struct TintGrid {
+    min_x: i32,
+    min_z: i32,
+    width: usize,
+    height: usize,
+    tints: Vec<Option<BiomeTints>>,
+}
+
+impl TintGrid {
+    /// Empty grid from `min_x` `min_z`, with the size in blocks
+    pub fn new(min_x: i32, min_z: i32, width: usize, height: usize) -> TintGrid {
+        TintGrid {
+            min_x,
+            min_z,
+            width,
+            height,
+            tints: vec![None; width * height],
+        }
+    }
+
+    fn index(&self, x: i32, z: i32) -> Option<usize> {
+        let local_x = usize::try_from(x - self.min_x).ok()?;
+        let local_z = usize::try_from(z - self.min_z).ok()?;
+
+        (local_x < self.width && local_z < self.height).then_some(local_z * self.width + local_x)
+    }
+
+    /// Set the tints of a column. Columns out of the grid are ignored
+    pub fn set(&mut self, x: i32, z: i32, tints: BiomeTints) {
+        if let Some(i) = self.index(x, z) {
+            self.tints[i] = Some(tints);
+        }
+    }
+
+    pub fn get(&self, x: i32, z: i32) -> Option<BiomeTints> {
+        self.tints[self.index(x, z)?]
+    }
+
+    /// Average the tints over the squares of `2 * radius + 1` columns around each column.
+    /// Missing columns, where there is no chunk, are left out of the averages
+    pub fn blend(&self, radius: usize) -> TintGrid {
+        // Sums of the grass, foliage and water channels, and the number of columns
+        let sums: Vec<[f32; 10]> = self
+            .tints
+            .iter()
+            .map(|tints| match tints {
+                Some(t) => {
+                    let mut sum = [0.0; 10];
+                    for (i, c) in t.grass.iter().chain(&t.foliage).chain(&t.water).enumerate() {
+                        sum[i] = *c as f32;
+                    }
+                    sum[9] = 1.0;
+                    sum
+                }
+                None => [0.0; 10],
+            })
+            .collect();
+
+        // Separable box blur, rows then columns
+        let rows = box_sums(&sums, self.width, self.height, radius, 1, self.width);
+        let blurred = box_sums(&rows, self.height, self.width, radius, self.width, 1);
+
+        let tints = self
+            .tints
+            .iter()
+            .zip(blurred)
+            .map(|(tints, sum)| {
+                tints.map(|_| {
+                    let channel = |i: usize| (sum[i] / sum[9]).round() as u8;
+                    BiomeTints {
+                        grass: [channel(0), channel(1), channel(2)],
+                        foliage: [channel(3), channel(4), channel(5)],
+                        water: [channel(6), channel(7), channel(8)],
+                    }
+                })
+            })
+            .collect();
+
+        TintGrid { tints, ..*self }
+    }
+}
+
+/// Sums over a window of `2 * radius + 1` values along lines of `length` values. Values of a
+/// line are `step` apart and the lines are `line_step` apart
+fn box_sums(
+    values: &[[f32; 10]],
+    length: usize,
+    lines: usize,
+    radius: usize,
+    step: usize,
+    line_step: usize,
+) -> Vec<[f32; 10]> {
+    let mut sums = vec![[0.0; 10]; values.len()];
+
+    for line in 0..lines {
+        let start = line * line_step;
+        let mut window = [0.0; 10];
+
+        // Values from -radius - 1 leave the window, values at +radius enter it
+        for i in 0..length + radius {
+            if i < length {
+                add(&mut window, &values[start + i * step], 1.0);
+            }
+            if i > 2 * radius {
+                add(
+                    &mut window,
+                    &values[start + (i - 2 * radius - 1) * step],
+                    -1.0,
+                );
+            }
+            if i >= radius {
+                sums[start + (i - radius) * step] = window;
+            }
+        }
+    }
+
+    sums
+}
+
+fn add(sum: &mut [f32; 10], values: &[f32; 10], sign: f32) {
+    for (s, v) in sum.iter_mut().zip(values) {
+        *s += v * sign;
+    }
+}
+
+/// Seed of the biome info noise of the game
+const BIOME_INFO_SEED: i64 = 2345;
+
+/// `java.util.Random`, the legacy random source of the game
+struct JavaRandom {
+    seed: i64,
+}
+
+impl JavaRandom {
+    const MULTIPLIER: i64 = 0x5_deec_e66d;
+    const MASK: i64 = (1 << 48) - 1;
+
+    fn new(seed: i64) -> JavaRandom {
+        JavaRandom {
+            seed: (seed ^ Self::MULTIPLIER) & Self::MASK,
+        }
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = (self.seed.wrapping_mul(Self::MULTIPLIER).wrapping_add(0xb)) & Self::MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+
+    fn next_int(&mut self, bound: i32) -> i32 {
+        if bound & (bound - 1) == 0 {
+            return ((bound as i64 * self.next(31) as i64) >> 31) as i32;
+        }
+
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 {
+                return value;
+            }
+        }
+    }
+
+    fn next_double(&mut self) -> f64 {
+        let high = (self.next(26) as i64) << 27;
+        (high + self.next(27) as i64) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+}
+
+const GRADIENTS: [[f64; 3]; 16] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+    [1.0, 1.0, 0.0],
+    [0.0, -1.0, 1.0],
+    [-1.0, 1.0, 0.0],
+    [0.0, -1.0, -1.0],
+];
+
+/// 2D simplex noise of the game. The biome info noise has a single octave, so it is the
+/// noise itself
+struct SimplexNoise {
+    permutations: [i32; 256],
+}
+
+impl SimplexNoise {
+    fn new(random: &mut JavaRandom) -> SimplexNoise {
+        // Origin, only used by the 3D noise
+        for _ in 0..3 {
+            random.next_double();
+        }
+
+        let mut permutations = [0; 256];
+        for (i, p) in permutations.iter_mut().enumerate() {
+            *p = i as i32;
+        }
+
+        for i in 0..256 {
+            let j = random.next_int(256 - i as i32) as usize;
+            permutations.swap(i, i + j);
+        }
+
+        SimplexNoise { permutations }
+    }
+
+    fn p(&self, i: i32) -> i32 {
+        self.permutations[(i & 255) as usize]
+    }
+
+    fn corner_noise(gradient: usize, x: f64, y: f64) -> f64 {
+        let t = 0.5 - x * x - y * y;
+        if t < 0.0 {
+            return 0.0;
+        }
+
+        let t = t * t;
+        let [gx, gy, _] = GRADIENTS[gradient];
+        t * t * (gx * x + gy * y)
+    }
+
+    fn get_value(&self, x: f64, y: f64) -> f64 {
+        let sqrt_3 = 3f64.sqrt();
+        let f2 = 0.5 * (sqrt_3 - 1.0);
+        let g2 = (3.0 - sqrt_3) / 6.0;
+
+        let s = (x + y) * f2;
+        let i = (x + s).floor() as i32;
+        let j = (y + s).floor() as i32;
+        let t = (i + j) as f64 * g2;
+        let x0 = x - (i as f64 - t);
+        let y0 = y - (j as f64 - t);
+
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+        let x1 = x0 - i1 as f64 + g2;
+        let y1 = y0 - j1 as f64 + g2;
+        let x2 = x0 - 1.0 + 2.0 * g2;
+        let y2 = y0 - 1.0 + 2.0 * g2;
+
+        let ii = i & 255;
+        let jj = j & 255;
+        let g0 = self.p(ii + self.p(jj)) % 12;
+        let g1 = self.p(ii + i1 + self.p(jj + j1)) % 12;
+        let g2_index = self.p(ii + 1 + self.p(jj + 1)) % 12;
+
+        let n0 = Self::corner_noise(g0 as usize, x0, y0);
+        let n1 = Self::corner_noise(g1 as usize, x1, y1);
+        let n2 = Self::corner_noise(g2_index as usize, x2, y2);
+
+        70.0 * (n0 + n1 + n2)
+    }
+}
@@ -55,7 +55,7 @@ pub fn temperature_color(value: f32) -> [u8; 3] {
     let cold = [64.0, 125.0, 237.0];
     let warm = [250.0, 118.0, 77.0];
 
-    let clamped_value = value.max(-1.0).min(2.0);
+    let clamped_value = value.clamp(-1.0, 2.0);
     let t = (clamped_value + 1.0) / (1.0 + 2.0);
 
     let r = (cold[0] * (1.0 - t) + warm[0] * t).round() as u8;
@@ -69,7 +69,7 @@ pub fn downfall_color(value: f32) -> [u8; 3] {
     let cold = [0.0, 0.0, 0.0];
     let warm = [255.0, 255.0, 255.0];
 
-    let t = value.max(0.0).min(1.0);
+    let t = value.clamp(0.0, 1.0);
 
     let r = (cold[0] * (1.0 - t) + warm[0] * t).round() as u8;
     let g = (cold[1] * (1.0 - t) + warm[1] * t).round() as u8;
@@ -91,3 +91,76 @@ pub fn linear_color(value: f32, min: f32, max: f32) -> [u8; 3] {
 
     [r, g, b]
 }
+
+/// Dark blue for light level 0 to yellow for level 15
+pub fn light_color(level: u8) -> [u8; 3] {
+    let dark = [20.0, 20.0, 60.0];
+    let bright = [255.0, 230.0, 120.0];
+
+    let t = level.min(15) as f32 / 15.0;
+
+    let r = (dark[0] * (1.0 - t) + bright[0] * t).round() as u8;
+    let g = (dark[1] * (1.0 - t) + bright[1] * t).round() as u8;
+    let b = (dark[2] * (1.0 - t) + bright[2] * t).round() as u8;
+
+    [r, g, b]
+}
+
+/// Hostile mobs only spawn where the block light is 0.
+/// Red if they can spawn at any time, orange if only at night, unchanged otherwise
+pub fn spawnability_color(rgb: [u8; 3], block_light: u8, sky_light: u8) -> [u8; 3] {
+    let tint = match (block_light, sky_light) {
+        (0, 0..=7) => [255.0, 0.0, 0.0],
+        (0, _) => [255.0, 150.0, 0.0],
+        _ => return rgb,
+    };
+
+    let mut tinted_color = [0u8; 3];
+    for i in 0..3 {
+        let val = 0.5 * rgb[i] as f32 + 0.5 * tint[i];
+        tinted_color[i] = val.round().clamp(0.0, 255.0) as u8;
+    }
+
+    tinted_color
+}
+
+/// Darken a color like at midnight, where the sky light is reduced by 11 levels
+pub fn night_color(rgb: [u8; 3], block_light: u8, sky_light: u8) -> [u8; 3] {
+    let moon_level = sky_light.saturating_sub(11);
+    let level = block_light.max(moon_level).min(15);
+    let brightness = 0.15 + 0.85 * (level as f32 / 15.0);
+
+    // Moonlight is slightly blue, torches are not
+    let tint = if block_light > moon_level {
+        [1.0, 1.0, 1.0]
+    } else {
+        [0.8, 0.85, 1.0]
+    };
+
+    let mut night = [0u8; 3];
+    for i in 0..3 {
+        let val = rgb[i] as f32 * brightness * tint[i];
+        night[i] = val.round().clamp(0.0, 255.0) as u8;
+    }
+
+    night
+}
+
+/// Faded grey version of a color, for the parts of a map that should not stand out
+pub fn grey_color(rgb: [u8; 3]) -> [u8; 3] {
+    let luma = 0.299 * rgb[0] as f32 + 0.587 * rgb[1] as f32 + 0.114 * rgb[2] as f32;
+    let val = (luma * 0.5 + 60.0).round().clamp(0.0, 255.0) as u8;
+
+    [val; 3]
+}
+
+/// Mix two colors, `t` being the weight of the second one
+pub fn blend_colors(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
+    let mut blended = [0u8; 3];
+    for i in 0..3 {
+        let val = a[i] as f32 * (1.0 - t) + b[i] as f32 * t;
+        blended[i] = val.round().clamp(0.0, 255.0) as u8;
+    }
+
+    blended
+}